@@ -0,0 +1,263 @@
+//! Color swatches and pickers for `Color` arguments, such as `$color[#ff00aa]`.
+//!
+//! A literal keeps its format when a new color is picked: hex stays hex,
+//! integers stay integers and named colors stay named whenever one matches.
+
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types::*;
+
+use crate::parser::{ParseResult, ParsedArg};
+use crate::server::ForgeScriptServer;
+use crate::utils::{offset_to_position, position_to_offset};
+
+/// The discord.js `Colors` accepted by name in color arguments.
+const NAMED_COLORS: &[(&str, u32)] = &[
+    ("Default", 0x000000),
+    ("White", 0xffffff),
+    ("Aqua", 0x1abc9c),
+    ("Green", 0x57f287),
+    ("Blue", 0x3498db),
+    ("Yellow", 0xfee75c),
+    ("Purple", 0x9b59b6),
+    ("LuminousVividPink", 0xe91e63),
+    ("Fuchsia", 0xeb459e),
+    ("Gold", 0xf1c40f),
+    ("Orange", 0xe67e22),
+    ("Red", 0xed4245),
+    ("Grey", 0x95a5a6),
+    ("Navy", 0x34495e),
+    ("DarkAqua", 0x11806a),
+    ("DarkGreen", 0x1f8b4c),
+    ("DarkBlue", 0x206694),
+    ("DarkPurple", 0x71368a),
+    ("DarkVividPink", 0xad1457),
+    ("DarkGold", 0xc27c0e),
+    ("DarkOrange", 0xa84300),
+    ("DarkRed", 0x992d22),
+    ("DarkGrey", 0x979c9f),
+    ("DarkerGrey", 0x7f8c8d),
+    ("LightGrey", 0xbcc0c0),
+    ("DarkNavy", 0x2c3e50),
+    ("Blurple", 0x5865f2),
+    ("Greyple", 0x99aab5),
+    ("DarkButNotBlack", 0x2c2f33),
+    ("NotQuiteBlack", 0x23272a),
+];
+
+/// How a color literal is written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ColorFormat {
+    /// `#rrggbb`, remembering whether the digits were uppercase.
+    Hex {
+        uppercase: bool,
+    },
+    Int,
+    Named,
+}
+
+/// Parses a color literal into its RGB value and format.
+fn parse_color(text: &str) -> Option<(u32, ColorFormat)> {
+    if let Some(hex) = text.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let rgb = u32::from_str_radix(hex, 16).ok()?;
+        let uppercase = hex.chars().any(|c| c.is_ascii_uppercase());
+        return Some((rgb, ColorFormat::Hex { uppercase }));
+    }
+    if text.bytes().all(|b| b.is_ascii_digit()) {
+        return text
+            .parse::<u32>()
+            .ok()
+            .filter(|&rgb| rgb <= 0xffffff)
+            .map(|rgb| (rgb, ColorFormat::Int));
+    }
+    NAMED_COLORS
+        .iter()
+        .find(|(name, _)| *name == text)
+        .map(|&(_, rgb)| (rgb, ColorFormat::Named))
+}
+
+/// Writes `rgb` in the given format; named colors without a match fall back to hex.
+fn format_color(rgb: u32, format: ColorFormat) -> String {
+    match format {
+        ColorFormat::Hex { uppercase: true } => format!("#{rgb:06X}"),
+        ColorFormat::Hex { uppercase: false } => format!("#{rgb:06x}"),
+        ColorFormat::Int => rgb.to_string(),
+        ColorFormat::Named => NAMED_COLORS
+            .iter()
+            .find(|&&(_, value)| value == rgb)
+            .map(|(name, _)| name.to_string())
+            .unwrap_or_else(|| format_color(rgb, ColorFormat::Hex { uppercase: false })),
+    }
+}
+
+fn to_lsp_color(rgb: u32) -> Color {
+    let channel = |shift: u32| ((rgb >> shift) & 0xff) as f32 / 255.0;
+    Color {
+        red: channel(16),
+        green: channel(8),
+        blue: channel(0),
+        alpha: 1.0,
+    }
+}
+
+fn from_lsp_color(color: &Color) -> u32 {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u32;
+    (channel(color.red) << 16) | (channel(color.green) << 8) | channel(color.blue)
+}
+
+/// Finds every literal passed to an argument whose type is `Color`.
+fn color_literals(parsed: &ParseResult) -> Vec<(usize, usize, u32)> {
+    let mut found = Vec::new();
+    for func in &parsed.functions {
+        let meta_args = func.meta.args.as_deref().unwrap_or_default();
+        for (i, (parts, (start, end))) in func.args.iter().flatten().enumerate() {
+            let Some(arg) = meta_args
+                .get(i)
+                .or_else(|| meta_args.last().filter(|a| a.rest))
+            else {
+                continue;
+            };
+            let is_color = match &arg.arg_type {
+                serde_json::Value::String(t) => t == "Color",
+                serde_json::Value::Array(types) => types.iter().any(|t| t == "Color"),
+                _ => false,
+            };
+            if is_color
+                && let [ParsedArg::Literal { text }] = parts.as_slice()
+                && let Some((rgb, _)) = parse_color(text)
+            {
+                found.push((*start, *end, rgb));
+            }
+        }
+    }
+    found
+}
+
+pub async fn handle_document_color(
+    server: &ForgeScriptServer,
+    params: DocumentColorParams,
+) -> Result<Vec<ColorInformation>> {
+    let uri = params.text_document.uri;
+    let Some(text) = server
+        .documents
+        .read()
+        .expect("Server: lock poisoned")
+        .get(&uri)
+        .cloned()
+    else {
+        return Ok(Vec::new());
+    };
+    let Some(parsed) = server
+        .parsed_cache
+        .read()
+        .expect("Server: lock poisoned")
+        .get(&uri)
+        .cloned()
+    else {
+        return Ok(Vec::new());
+    };
+
+    Ok(color_literals(&parsed)
+        .into_iter()
+        .map(|(start, end, rgb)| ColorInformation {
+            range: Range::new(
+                offset_to_position(&text, start),
+                offset_to_position(&text, end),
+            ),
+            color: to_lsp_color(rgb),
+        })
+        .collect())
+}
+
+pub async fn handle_color_presentation(
+    server: &ForgeScriptServer,
+    params: ColorPresentationParams,
+) -> Result<Vec<ColorPresentation>> {
+    let current = server
+        .documents
+        .read()
+        .expect("Server: lock poisoned")
+        .get(&params.text_document.uri)
+        .and_then(|text| {
+            let start = position_to_offset(text, params.range.start)?;
+            let end = position_to_offset(text, params.range.end)?;
+            text.get(start..end).map(str::to_string)
+        });
+    let format = current
+        .as_deref()
+        .and_then(parse_color)
+        .map(|(_, format)| format)
+        .unwrap_or(ColorFormat::Hex { uppercase: false });
+
+    // The literal's own format comes first; editors apply the first presentation.
+    let rgb = from_lsp_color(&params.color);
+    let mut labels = vec![format_color(rgb, format)];
+    for alternative in [
+        ColorFormat::Hex { uppercase: false },
+        ColorFormat::Int,
+        ColorFormat::Named,
+    ] {
+        let label = format_color(rgb, alternative);
+        if !labels.contains(&label) {
+            labels.push(label);
+        }
+    }
+
+    Ok(labels
+        .into_iter()
+        .map(|label| ColorPresentation {
+            text_edit: Some(TextEdit::new(params.range, label.clone())),
+            label,
+            additional_text_edits: None,
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(text: &str) -> Option<String> {
+        parse_color(text).map(|(rgb, format)| format_color(rgb, format))
+    }
+
+    #[test]
+    fn literals_keep_their_format() {
+        for text in [
+            "#ff00aa",
+            "#FF00AA",
+            "16711850",
+            "0",
+            "Blurple",
+            "DarkButNotBlack",
+        ] {
+            assert_eq!(round_trip(text).as_deref(), Some(text));
+        }
+        assert_eq!(
+            parse_color("#FfA000"),
+            Some((0xffa000, ColorFormat::Hex { uppercase: true }))
+        );
+    }
+
+    #[test]
+    fn rejects_malformed_literals() {
+        for text in ["#fff", "#gg0000", "16777216", "blurple", ""] {
+            assert_eq!(parse_color(text), None, "{text}");
+        }
+    }
+
+    #[test]
+    fn named_colors_without_a_match_fall_back_to_hex() {
+        assert_eq!(format_color(0x123456, ColorFormat::Named), "#123456");
+        assert_eq!(format_color(0x5865f2, ColorFormat::Named), "Blurple");
+    }
+
+    #[test]
+    fn lsp_colors_round_trip() {
+        for rgb in [0x000000, 0xffffff, 0x5865f2, 0x0a0b0c] {
+            assert_eq!(from_lsp_color(&to_lsp_color(rgb)), rgb);
+        }
+    }
+}
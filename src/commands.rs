@@ -8,6 +8,10 @@ pub async fn handle_execute_command(
     server: &ForgeScriptServer,
     params: ExecuteCommandParams,
 ) -> Result<Option<serde_json::Value>> {
+    if params.command == "forge/configSchema" {
+        return Ok(Some(crate::config_schema::config_schema()));
+    }
+
     if params.command == "forge/cursorMoved"
         && let Some(args) = params.arguments.get(0)
     {
@@ -25,11 +29,7 @@ pub async fn handle_execute_command(
                 let up_to_cursor = get_text_up_to_cursor(text, moved.position);
                 let (name, open) = find_active_function_call(&up_to_cursor)?;
                 let idx = compute_active_param_index(&up_to_cursor[open + 1..]) as usize;
-                let mgr = server
-                    .manager
-                    .read()
-                    .expect("Server: lock poisoned")
-                    .clone();
+                let mgr = server.manager_for(&moved.uri);
                 let func = mgr.get(&format!("${name}"))?;
                 let args = func.args.as_ref()?;
                 let arg_idx = if idx >= args.len() && args.last()?.rest {
@@ -34,13 +34,17 @@ pub async fn handle_completion(
         .ok_or(tower_lsp::jsonrpc::Error::invalid_params(
             "Document not found",
         ))?;
-    let mgr = server
-        .manager
+    let mgr = server.manager_for(&uri);
+
+    let functions = server
+        .parsed_cache
         .read()
         .expect("Server: lock poisoned")
-        .clone();
-
-    let res = get_completions(&text, position, &mgr);
+        .get(&uri)
+        .map(|parsed| parsed.functions.clone())
+        .unwrap_or_default();
+    let res = variable_completions(&text, position, &functions)
+        .or_else(|| get_completions(&text, position, &mgr));
 
     forge_log(
         LogLevel::Debug,
@@ -54,6 +58,10 @@ pub fn get_completions(
     position: Position,
     mgr: &crate::metadata::MetadataManager,
 ) -> Option<CompletionResponse> {
+    if let Some(events) = event_completions(text, position, mgr) {
+        return Some(events);
+    }
+
     let text_up_to_cursor = get_text_up_to_cursor(text, position);
     if let Some((func_name, open_idx)) = find_active_function_call(&text_up_to_cursor) {
         let param_idx = compute_active_param_index(&text_up_to_cursor[open_idx + 1..]) as usize;
@@ -131,6 +139,105 @@ pub fn get_completions(
     }))
 }
 
+/// Completes variable names in the first argument of `$get`, `$let`, `$getVar` and `$setVar`.
+pub fn variable_completions(
+    text: &str,
+    position: Position,
+    functions: &[crate::parser::ParsedFunction],
+) -> Option<CompletionResponse> {
+    let text_up_to_cursor = get_text_up_to_cursor(text, position);
+    let (func_name, open_idx) = find_active_function_call(&text_up_to_cursor)?;
+    if compute_active_param_index(&text_up_to_cursor[open_idx + 1..]) != 0 {
+        return None;
+    }
+    let (scope, _) = crate::variables::classify(&func_name)?;
+
+    let offset = position_to_offset(text, position)?;
+    let index = crate::variables::VariableIndex::build(text, functions);
+    let detail = match scope {
+        crate::variables::VarScope::Local => "Variable ($let)",
+        crate::variables::VarScope::Global => "Global variable ($setVar)",
+    };
+    let items = index
+        .names_visible_at(text, offset, scope)
+        .into_iter()
+        .map(|name| CompletionItem {
+            label: name.clone(),
+            kind: Some(CompletionItemKind::VARIABLE),
+            detail: Some(detail.to_string()),
+            insert_text: Some(name),
+            ..Default::default()
+        })
+        .collect();
+
+    Some(CompletionResponse::List(CompletionList {
+        is_incomplete: false,
+        items,
+    }))
+}
+
+/// Completes event names inside the string of a command's `type:` field.
+fn event_completions(
+    text: &str,
+    position: Position,
+    mgr: &crate::metadata::MetadataManager,
+) -> Option<CompletionResponse> {
+    let line = text.lines().nth(position.line as usize).unwrap_or("");
+    let offset =
+        position_to_offset(line, Position::new(0, position.character)).unwrap_or(line.len());
+    let before = &line[..offset];
+
+    let typed = before.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_');
+    let head = typed.strip_suffix(['"', '\'', '`'])?.trim_end();
+    let key = head.strip_suffix(':')?.trim_end().strip_suffix("type")?;
+    if key
+        .chars()
+        .next_back()
+        .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '$')
+    {
+        return None;
+    }
+
+    let prefix = before[typed.len()..].to_lowercase();
+    let start_char = typed.encode_utf16().count() as u32;
+    let range = Range::new(Position::new(position.line, start_char), position);
+    let items: Vec<CompletionItem> = mgr
+        .events
+        .read()
+        .expect("Server: lock poisoned")
+        .iter()
+        .filter(|event| event.name.to_lowercase().contains(&prefix))
+        .map(|event| {
+            let mut doc = event.description.clone();
+            if let Some(intents) = event.intents.as_ref().filter(|i| !i.is_empty()) {
+                doc.push_str(&format!("\n\n**Intents:** {}", intents.join(", ")));
+            }
+            CompletionItem {
+                label: event.name.clone(),
+                kind: Some(CompletionItemKind::EVENT),
+                detail: Some(format!("Event (v{})", event.version)),
+                documentation: Some(Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: doc,
+                })),
+                text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+                    range,
+                    new_text: event.name.clone(),
+                })),
+                ..Default::default()
+            }
+        })
+        .collect();
+    if items.is_empty() {
+        return None;
+    }
+
+    Some(CompletionResponse::List(CompletionList {
+        is_incomplete: false,
+        items,
+    }))
+}
+
 pub(crate) fn build_completion_item(
     f: Arc<crate::metadata::Function>,
     modifier: &str,
@@ -143,14 +250,18 @@ pub(crate) fn build_completion_item(
         base.clone()
     };
 
+    let mut detail = f
+        .extension
+        .clone()
+        .unwrap_or_else(|| f.category.clone().unwrap_or_else(|| "Function".to_string()));
+    if let Some(since) = crate::version::since_label(&f) {
+        detail.push_str(&format!(" ({since})"));
+    }
+
     CompletionItem {
         label: name.clone(),
         kind: Some(CompletionItemKind::FUNCTION),
-        detail: Some(
-            f.extension
-                .clone()
-                .unwrap_or_else(|| f.category.clone().unwrap_or_else(|| "Function".to_string())),
-        ),
+        detail: Some(detail),
         documentation: Some(Documentation::MarkupContent(MarkupContent {
             kind: MarkupKind::Markdown,
             value: build_completion_markdown(&f),
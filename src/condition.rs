@@ -0,0 +1,515 @@
+//! Parsing of condition arguments into a small expression tree.
+//!
+//! Conditions are comparisons (`==`, `!=`, `>=`, `<=`, `>`, `<`) joined by `&&`
+//! and `||`, where `&&` binds tighter. Operands are raw text and may contain
+//! nested function calls; operators inside brackets belong to those calls and
+//! are ignored, as are `<`, `>` and `=` that are part of an operand's text.
+
+use crate::parser::ParsedFunction;
+use crate::utils::{find_matching_bracket, is_escaped};
+
+/// A comparison operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Ge,
+    Le,
+    Gt,
+    Lt,
+}
+
+/// A boolean connective.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogicalOp {
+    And,
+    Or,
+}
+
+/// Side of a comparison, or a bare boolean value, with its byte span.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operand {
+    pub text: String,
+    pub span: (usize, usize),
+}
+
+/// A parsed condition expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Condition {
+    Logical {
+        op: LogicalOp,
+        op_span: (usize, usize),
+        lhs: Box<Condition>,
+        rhs: Box<Condition>,
+    },
+    Compare {
+        lhs: Operand,
+        op: CompareOp,
+        op_span: (usize, usize),
+        rhs: Operand,
+    },
+    Value(Operand),
+}
+
+impl Condition {
+    /// Returns the spans of every operator in the expression, in source order.
+    pub fn operator_spans(&self) -> Vec<(usize, usize)> {
+        let mut spans = Vec::new();
+        self.collect_operators(&mut spans);
+        spans.sort_unstable();
+        spans
+    }
+
+    fn collect_operators(&self, spans: &mut Vec<(usize, usize)>) {
+        match self {
+            Condition::Logical {
+                op_span, lhs, rhs, ..
+            } => {
+                spans.push(*op_span);
+                lhs.collect_operators(spans);
+                rhs.collect_operators(spans);
+            }
+            Condition::Compare { op_span, .. } => spans.push(*op_span),
+            Condition::Value(_) => {}
+        }
+    }
+}
+
+/// Why a condition could not be parsed, and where.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConditionError {
+    pub message: String,
+    pub span: (usize, usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Compare(CompareOp),
+    Logical(LogicalOp),
+    /// A single `=`, which is almost always a mistyped `==`.
+    Assign,
+}
+
+/// Operator spellings, longest first so that `>=` wins over `>`.
+const OPERATORS: [(&str, Op); 9] = [
+    ("==", Op::Compare(CompareOp::Eq)),
+    ("!=", Op::Compare(CompareOp::Ne)),
+    (">=", Op::Compare(CompareOp::Ge)),
+    ("<=", Op::Compare(CompareOp::Le)),
+    ("&&", Op::Logical(LogicalOp::And)),
+    ("||", Op::Logical(LogicalOp::Or)),
+    (">", Op::Compare(CompareOp::Gt)),
+    ("<", Op::Compare(CompareOp::Lt)),
+    ("=", Op::Assign),
+];
+
+/// Finds the operators of `text` that are outside of brackets; spans are offset by `base`.
+///
+/// `<`, `>` and `=` also appear in plain text such as `<@123>` mentions or URL
+/// query strings, so they only count as operators between two operands.
+fn lex(text: &str, base: usize) -> Vec<(Op, (usize, usize))> {
+    let mut candidates = Vec::new();
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < text.len() {
+        let rest = &text[i..];
+        if rest.starts_with('[') && !is_escaped(text, i) {
+            depth += 1;
+        } else if rest.starts_with(']') && !is_escaped(text, i) {
+            depth = depth.saturating_sub(1);
+        } else if depth == 0
+            && let Some((spelling, op)) = OPERATORS.iter().find(|(s, _)| {
+                // In `<@123>==`, the `>` closes the operand and `==` compares.
+                rest.starts_with(s) && !(matches!(*s, ">=" | "<=") && rest[2..].starts_with('='))
+            })
+        {
+            candidates.push((*op, (i, i + spelling.len())));
+            i += spelling.len();
+            continue;
+        }
+        i += rest.chars().next().map_or(1, char::len_utf8);
+    }
+
+    // Whether the candidate at `index` has operand text on both sides.
+    let between_operands = |index: usize| {
+        let (_, (start, end)) = candidates[index];
+        let before = index.checked_sub(1).map_or(0, |p| candidates[p].1.1);
+        let after = candidates
+            .get(index + 1)
+            .map_or(text.len(), |(_, span)| span.0);
+        !text[before..start].trim().is_empty() && !text[end..after].trim().is_empty()
+    };
+    let lone = |op: Op| matches!(op, Op::Compare(CompareOp::Gt | CompareOp::Lt));
+    let kept: Vec<bool> = (0..candidates.len())
+        .map(|index| !lone(candidates[index].0) || between_operands(index))
+        .collect();
+    // Whether a kept comparison operator shares the comparison of the candidate at `index`.
+    let compared = |index: usize| {
+        let is_compare =
+            |&(i, (op, _)): &(usize, &(Op, _))| kept[i] && matches!(op, Op::Compare(_));
+        let same_side = |(_, (op, _)): &(usize, &(Op, _))| !matches!(op, Op::Logical(_));
+        let before = candidates[..index].iter().enumerate().rev();
+        let after = candidates.iter().enumerate().skip(index + 1);
+        before.take_while(same_side).any(|c| is_compare(&c))
+            || after.take_while(same_side).any(|c| is_compare(&c))
+    };
+
+    let mut ops = Vec::new();
+    for (index, &(op, span)) in candidates.iter().enumerate() {
+        // A `=` between operands of a comparison that has an operator is operand text.
+        if !kept[index] || (op == Op::Assign && between_operands(index) && compared(index)) {
+            continue;
+        }
+        ops.push((op, (base + span.0, base + span.1)));
+    }
+    ops
+}
+
+/// Returns the spans of the operators written in a condition, even a malformed one.
+pub fn operator_spans(text: &str, base: usize) -> Vec<(usize, usize)> {
+    lex(text, base)
+        .into_iter()
+        .filter(|(op, _)| *op != Op::Assign)
+        .map(|(_, span)| span)
+        .collect()
+}
+
+/// Parses a condition whose text starts at byte offset `base` of the document.
+pub fn parse_condition(text: &str, base: usize) -> Result<Condition, ConditionError> {
+    let ops = lex(text, base);
+    if let Some((_, span)) = ops.iter().find(|(op, _)| *op == Op::Assign) {
+        return Err(ConditionError {
+            message: "`=` is not a comparison operator; use `==`".to_string(),
+            span: *span,
+        });
+    }
+    parse_logical(text, base, (base, base + text.len()), &ops, LogicalOp::Or)
+}
+
+/// Splits `range` on the connective `op` (then on tighter ones) and folds left.
+fn parse_logical(
+    text: &str,
+    base: usize,
+    range: (usize, usize),
+    ops: &[(Op, (usize, usize))],
+    op: LogicalOp,
+) -> Result<Condition, ConditionError> {
+    let spelling = match op {
+        LogicalOp::And => "&&",
+        LogicalOp::Or => "||",
+    };
+    // An empty side is reported on the connective next to it.
+    let inner = |range: (usize, usize), near: (usize, usize)| {
+        if text[range.0 - base..range.1 - base].trim().is_empty() {
+            return Err(ConditionError {
+                message: format!("Missing operand of `{spelling}`"),
+                span: near,
+            });
+        }
+        parse_inner(text, base, range, ops, op)
+    };
+
+    let mut start = range.0;
+    let mut result: Option<(Condition, (usize, usize))> = None;
+    let separators = ops
+        .iter()
+        .filter(|(o, span)| *o == Op::Logical(op) && span.0 >= range.0 && span.1 <= range.1)
+        .map(|(_, span)| *span);
+    for span in separators {
+        let operand = inner((start, span.0), span)?;
+        result = Some(match result {
+            None => (operand, span),
+            Some((lhs, op_span)) => (
+                Condition::Logical {
+                    op,
+                    op_span,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(operand),
+                },
+                span,
+            ),
+        });
+        start = span.1;
+    }
+    let last = match result {
+        Some((_, op_span)) => inner((start, range.1), op_span)?,
+        None => parse_inner(text, base, range, ops, op)?,
+    };
+    Ok(match result {
+        None => last,
+        Some((lhs, op_span)) => Condition::Logical {
+            op,
+            op_span,
+            lhs: Box::new(lhs),
+            rhs: Box::new(last),
+        },
+    })
+}
+
+/// Parses one side of the connective `op`, i.e. an expression with only tighter operators.
+fn parse_inner(
+    text: &str,
+    base: usize,
+    range: (usize, usize),
+    ops: &[(Op, (usize, usize))],
+    op: LogicalOp,
+) -> Result<Condition, ConditionError> {
+    match op {
+        LogicalOp::Or => parse_logical(text, base, range, ops, LogicalOp::And),
+        LogicalOp::And => parse_comparison(text, base, range, ops),
+    }
+}
+
+/// Parses a single comparison or boolean value within `range`.
+fn parse_comparison(
+    text: &str,
+    base: usize,
+    range: (usize, usize),
+    ops: &[(Op, (usize, usize))],
+) -> Result<Condition, ConditionError> {
+    let comparisons: Vec<(CompareOp, (usize, usize))> = ops
+        .iter()
+        .filter(|(_, span)| span.0 >= range.0 && span.1 <= range.1)
+        .filter_map(|(op, span)| match op {
+            Op::Compare(c) => Some((*c, *span)),
+            _ => None,
+        })
+        .collect();
+    let operand = |(start, end): (usize, usize)| {
+        let raw = &text[start - base..end - base];
+        let lead = raw.len() - raw.trim_start().len();
+        let trimmed = raw.trim();
+        Operand {
+            text: trimmed.to_string(),
+            span: (start + lead, start + lead + trimmed.len()),
+        }
+    };
+
+    match comparisons.as_slice() {
+        [] => {
+            let value = operand(range);
+            if value.text.is_empty() {
+                return Err(ConditionError {
+                    message: "Missing operand".to_string(),
+                    span: range,
+                });
+            }
+            if !is_boolean_value(&value.text) {
+                return Err(ConditionError {
+                    message: format!("`{}` is not a comparison or boolean", value.text),
+                    span: value.span,
+                });
+            }
+            Ok(Condition::Value(value))
+        }
+        [(op, op_span)] => {
+            let lhs = operand((range.0, op_span.0));
+            let rhs = operand((op_span.1, range.1));
+            let spelling = &text[op_span.0 - base..op_span.1 - base];
+            if lhs.text.is_empty() || rhs.text.is_empty() {
+                let side = if lhs.text.is_empty() { "left" } else { "right" };
+                return Err(ConditionError {
+                    message: format!("Missing {side} operand of `{spelling}`"),
+                    span: *op_span,
+                });
+            }
+            Ok(Condition::Compare {
+                lhs,
+                op: *op,
+                op_span: *op_span,
+                rhs,
+            })
+        }
+        [_, (_, second), ..] => Err(ConditionError {
+            message: "Comparisons cannot be chained; join them with `&&` or `||`".to_string(),
+            span: *second,
+        }),
+    }
+}
+
+/// Whether an operand can stand alone as a condition: a boolean or exactly one call.
+fn is_boolean_value(text: &str) -> bool {
+    if text.eq_ignore_ascii_case("true") || text.eq_ignore_ascii_case("false") {
+        return true;
+    }
+    let Some(rest) = text.strip_prefix('$') else {
+        return false;
+    };
+    match rest.find('[') {
+        Some(open) => find_matching_bracket(text, open + 1) == Some(text.len() - 1),
+        None => rest
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | '!' | '#')),
+    }
+}
+
+/// Yields the span of every argument that metadata marks as a condition.
+pub fn condition_args<'a>(
+    functions: impl IntoIterator<Item = &'a ParsedFunction>,
+) -> impl Iterator<Item = (&'a ParsedFunction, (usize, usize))> {
+    functions.into_iter().flat_map(|func| {
+        let meta_args = func.meta.args.as_deref().unwrap_or_default();
+        func.args
+            .iter()
+            .flatten()
+            .enumerate()
+            .filter(move |(i, _)| {
+                meta_args
+                    .get(*i)
+                    .or_else(|| meta_args.last().filter(|a| a.rest))
+                    .and_then(|a| a.condition)
+                    == Some(true)
+            })
+            .map(move |(_, (_, span))| (func, *span))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(text: &str) -> (String, (usize, usize)) {
+        let e = parse_condition(text, 0).expect_err(text);
+        (e.message, e.span)
+    }
+
+    #[test]
+    fn parses_comparison_with_offset_spans() {
+        assert_eq!(
+            parse_condition("a == b", 10),
+            Ok(Condition::Compare {
+                lhs: Operand {
+                    text: "a".to_string(),
+                    span: (10, 11),
+                },
+                op: CompareOp::Eq,
+                op_span: (12, 14),
+                rhs: Operand {
+                    text: "b".to_string(),
+                    span: (15, 16),
+                },
+            })
+        );
+    }
+
+    #[test]
+    fn parses_each_operator() {
+        for (text, op) in [
+            ("a==b", CompareOp::Eq),
+            ("a!=b", CompareOp::Ne),
+            ("a>=b", CompareOp::Ge),
+            ("a<=b", CompareOp::Le),
+            ("a>b", CompareOp::Gt),
+            ("a<b", CompareOp::Lt),
+        ] {
+            match parse_condition(text, 0) {
+                Ok(Condition::Compare { op: parsed, .. }) => assert_eq!(parsed, op, "{text}"),
+                other => panic!("{text}: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let text = "a >= 1 && b != 2 || $flag";
+        let condition = parse_condition(text, 0).unwrap();
+        let Condition::Logical {
+            op: LogicalOp::Or,
+            lhs,
+            rhs,
+            ..
+        } = &condition
+        else {
+            panic!("{condition:?}");
+        };
+        assert!(matches!(
+            **lhs,
+            Condition::Logical {
+                op: LogicalOp::And,
+                ..
+            }
+        ));
+        assert!(matches!(**rhs, Condition::Value(_)));
+        assert_eq!(
+            condition.operator_spans(),
+            [(2, 4), (7, 9), (12, 14), (17, 19)]
+        );
+    }
+
+    #[test]
+    fn ignores_operators_inside_calls() {
+        let condition = parse_condition("$get[a==b]==1", 0).unwrap();
+        assert_eq!(condition.operator_spans(), [(10, 12)]);
+    }
+
+    #[test]
+    fn treats_equals_in_operands_as_text() {
+        let Ok(Condition::Compare { rhs, .. }) =
+            parse_condition("$get[url]==https://x.com/?a=b&c=d", 0)
+        else {
+            panic!("URL query string");
+        };
+        assert_eq!(rhs.text, "https://x.com/?a=b&c=d");
+        let Ok(Condition::Compare { lhs, .. }) = parse_condition("key=value != $get[pair]", 0)
+        else {
+            panic!("literal `=`");
+        };
+        assert_eq!(lhs.text, "key=value");
+    }
+
+    #[test]
+    fn treats_angle_brackets_of_mentions_as_text() {
+        let condition = parse_condition("<@123> == $get[user] || $authorID==<@!456>", 0).unwrap();
+        assert_eq!(condition.operator_spans(), [(7, 9), (21, 23), (33, 35)]);
+        assert_eq!(operator_spans("<@123>==", 0), [(6, 8)]);
+        assert!(matches!(
+            parse_condition("<@1>", 0),
+            Err(ConditionError { span: (0, 4), .. })
+        ));
+    }
+
+    #[test]
+    fn reports_single_equals() {
+        assert_eq!(
+            error("a=b"),
+            (
+                "`=` is not a comparison operator; use `==`".to_string(),
+                (1, 2)
+            )
+        );
+        assert_eq!(
+            error("a!==b"),
+            (
+                "`=` is not a comparison operator; use `==`".to_string(),
+                (3, 4)
+            )
+        );
+    }
+
+    #[test]
+    fn reports_missing_operands() {
+        assert_eq!(
+            error("a>="),
+            ("Missing right operand of `>=`".to_string(), (1, 3))
+        );
+        assert_eq!(
+            error("==b"),
+            ("Missing left operand of `==`".to_string(), (0, 2))
+        );
+        assert_eq!(
+            error("5>=3&&"),
+            ("Missing operand of `&&`".to_string(), (4, 6))
+        );
+    }
+
+    #[test]
+    fn reports_chained_comparisons() {
+        assert_eq!(
+            error("a == b == c"),
+            (
+                "Comparisons cannot be chained; join them with `&&` or `||`".to_string(),
+                (7, 9)
+            )
+        );
+    }
+}
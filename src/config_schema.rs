@@ -0,0 +1,830 @@
+//! JSON Schema and validation for `forgeconfig.json`.
+//!
+//! The schema is generated from [`ForgeConfig`] so it cannot drift from what the
+//! server reads. Validation walks a span-aware JSON tree against that schema, so
+//! unknown keys and type mismatches are reported at their exact location.
+
+use std::collections::HashSet;
+
+use serde_json::Value as JsonValue;
+
+use crate::metadata::{Function, FunctionConflict, is_custom, origin_of};
+use crate::parser::{Diagnostic, Severity};
+use crate::utils::ForgeConfig;
+
+/// File name the server looks for in each workspace folder.
+pub const CONFIG_FILE_NAME: &str = "forgeconfig.json";
+
+/// Whether a document is a ForgeLSP configuration file.
+pub fn is_config_uri(uri: &lsp_types::Url) -> bool {
+    uri.path_segments()
+        .and_then(|mut segments| segments.next_back())
+        .is_some_and(|name| name == CONFIG_FILE_NAME)
+}
+
+/// Returns the JSON Schema describing `forgeconfig.json`.
+pub fn config_schema() -> JsonValue {
+    schemars::schema_for!(ForgeConfig).to_value()
+}
+
+/// Validates the text of a `forgeconfig.json` file against the generated schema.
+pub fn validate_config(text: &str) -> Vec<Diagnostic> {
+    let root = match JsonScanner::new(text).parse_document() {
+        Ok(root) => root,
+        Err((message, offset)) => {
+            let mut start = offset.min(text.len());
+            while !text.is_char_boundary(start) {
+                start -= 1;
+            }
+            // Underline the offending character, however many bytes it takes.
+            let width = text[start..].chars().next().map_or(0, char::len_utf8);
+            return vec![Diagnostic {
+                message: format!("Invalid JSON: {message}"),
+                start,
+                end: start + width,
+                severity: Severity::Error,
+                code: None,
+            }];
+        }
+    };
+
+    let schema = config_schema();
+    let mut validator = Validator {
+        root: &schema,
+        diagnostics: Vec::new(),
+    };
+    validator.check(&schema, &root, "");
+    validator.diagnostics
+}
+
+/// Reports the name conflicts a configuration causes at their definitions in its text.
+///
+/// Inline custom functions are flagged at their name; metadata sources shadowing
+/// another source are flagged once at their `urls` entry.
+pub fn conflict_diagnostics(text: &str, conflicts: &[FunctionConflict]) -> Vec<Diagnostic> {
+    let Ok(root) = JsonScanner::new(text).parse_document() else {
+        return Vec::new();
+    };
+    let NodeValue::Object(members) = &root.value else {
+        return Vec::new();
+    };
+    let field = |key: &str| members.iter().find(|m| m.key == key).map(|m| &m.value);
+    let is_inline = |func: &Function| is_custom(func) && func.local_path.is_none();
+
+    let mut diagnostics = Vec::new();
+    // Shadowed names per (shadowing source, shadowed source), in first-seen order.
+    let mut shadowed_sources: Vec<(&str, String, Vec<&str>)> = Vec::new();
+    for conflict in conflicts {
+        if is_inline(&conflict.kept) || is_inline(&conflict.shadowed) {
+            if let Some(node) =
+                field("custom_functions").and_then(|n| inline_definition(n, &conflict.name))
+            {
+                diagnostics.push(warning(node, conflict.custom_message()));
+            }
+            continue;
+        }
+        let (Some(url), false) = (&conflict.kept.source_url, is_custom(&conflict.kept)) else {
+            continue;
+        };
+        let shadowed = origin_of(&conflict.shadowed);
+        match shadowed_sources
+            .iter_mut()
+            .find(|(kept, other, _)| *kept == url.as_str() && *other == shadowed)
+        {
+            Some((_, _, names)) => names.push(&conflict.name),
+            None => shadowed_sources.push((url, shadowed, vec![&conflict.name])),
+        }
+    }
+
+    let Some(NodeValue::Array(urls)) = field("urls").map(|n| &n.value) else {
+        return diagnostics;
+    };
+    for (url, shadowed, mut names) in shadowed_sources {
+        let Some(node) = urls.iter().find(|item| {
+            matches!(&item.value, NodeValue::String(raw)
+                if crate::utils::resolve_github_shorthand(raw.clone()) == url)
+        }) else {
+            continue;
+        };
+        names.sort_unstable();
+        let listed = names
+            .iter()
+            .take(5)
+            .map(|name| format!("`{name}`"))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let more = names.len().saturating_sub(5);
+        let message = format!(
+            "This source shadows {} function(s) from {shadowed}: {listed}{}",
+            names.len(),
+            if more > 0 {
+                format!(" and {more} more")
+            } else {
+                String::new()
+            }
+        );
+        diagnostics.push(warning(node, message));
+    }
+    diagnostics
+}
+
+/// Finds the `name` (or alias) value of the inline custom function registered as `name`.
+fn inline_definition<'n>(functions: &'n Node, name: &str) -> Option<&'n Node> {
+    let NodeValue::Array(items) = &functions.value else {
+        return None;
+    };
+    let matches = |node: &Node| {
+        matches!(&node.value, NodeValue::String(value)
+            if value.trim_start_matches('$').eq_ignore_ascii_case(name.trim_start_matches('$')))
+    };
+    items.iter().find_map(|item| {
+        let NodeValue::Object(members) = &item.value else {
+            return None;
+        };
+        members
+            .iter()
+            .find_map(|member| match (&member.key[..], &member.value.value) {
+                ("name", _) if matches(&member.value) => Some(&member.value),
+                ("alias", NodeValue::Array(aliases)) => aliases.iter().find(|a| matches(a)),
+                _ => None,
+            })
+    })
+}
+
+fn warning(node: &Node, message: String) -> Diagnostic {
+    Diagnostic {
+        message,
+        start: node.start,
+        end: node.end,
+        severity: Severity::Warning,
+        code: None,
+    }
+}
+
+// ── Span-Aware JSON Tree ────────────────────────────────────────────────────
+
+#[derive(Debug)]
+struct Node {
+    value: NodeValue,
+    start: usize,
+    end: usize,
+}
+
+#[derive(Debug)]
+enum NodeValue {
+    Null,
+    Bool(bool),
+    Number { value: f64, integer: bool },
+    String(String),
+    Array(Vec<Node>),
+    Object(Vec<Member>),
+}
+
+#[derive(Debug)]
+struct Member {
+    key: String,
+    key_start: usize,
+    key_end: usize,
+    value: Node,
+}
+
+impl Node {
+    fn kind(&self) -> &'static str {
+        match &self.value {
+            NodeValue::Null => "null",
+            NodeValue::Bool(_) => "boolean",
+            NodeValue::Number { integer: true, .. } => "integer",
+            NodeValue::Number { .. } => "number",
+            NodeValue::String(_) => "string",
+            NodeValue::Array(_) => "array",
+            NodeValue::Object(_) => "object",
+        }
+    }
+
+    fn matches_type(&self, ty: &str) -> bool {
+        match ty {
+            "number" => matches!(self.value, NodeValue::Number { .. }),
+            other => self.kind() == other,
+        }
+    }
+
+    fn to_json(&self) -> JsonValue {
+        match &self.value {
+            NodeValue::Null => JsonValue::Null,
+            NodeValue::Bool(b) => JsonValue::Bool(*b),
+            NodeValue::Number { value, .. } => serde_json::Number::from_f64(*value)
+                .map(JsonValue::Number)
+                .unwrap_or(JsonValue::Null),
+            NodeValue::String(s) => JsonValue::String(s.clone()),
+            NodeValue::Array(items) => JsonValue::Array(items.iter().map(Node::to_json).collect()),
+            NodeValue::Object(members) => JsonValue::Object(
+                members
+                    .iter()
+                    .map(|m| (m.key.clone(), m.value.to_json()))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+type ScanResult<T> = Result<T, (String, usize)>;
+
+/// Minimal JSON parser that keeps the byte span of every value and key.
+struct JsonScanner<'a> {
+    text: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> JsonScanner<'a> {
+    fn new(text: &'a str) -> Self {
+        Self {
+            text,
+            bytes: text.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn parse_document(mut self) -> ScanResult<Node> {
+        let node = self.parse_value()?;
+        self.skip_whitespace();
+        if self.pos < self.bytes.len() {
+            return Err((
+                "unexpected content after the end of the document".into(),
+                self.pos,
+            ));
+        }
+        Ok(node)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .bytes
+            .get(self.pos)
+            .is_some_and(|b| b.is_ascii_whitespace())
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, byte: u8) -> ScanResult<()> {
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) == Some(&byte) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err((format!("expected '{}'", byte as char), self.pos))
+        }
+    }
+
+    fn parse_value(&mut self) -> ScanResult<Node> {
+        self.skip_whitespace();
+        let start = self.pos;
+        let value = match self.bytes.get(self.pos) {
+            Some(b'{') => self.parse_object()?,
+            Some(b'[') => self.parse_array()?,
+            Some(b'"') => NodeValue::String(self.parse_string()?),
+            Some(b'-' | b'0'..=b'9') => self.parse_number()?,
+            Some(b't') => self.parse_literal("true", NodeValue::Bool(true))?,
+            Some(b'f') => self.parse_literal("false", NodeValue::Bool(false))?,
+            Some(b'n') => self.parse_literal("null", NodeValue::Null)?,
+            Some(_) => return Err(("expected a value".into(), self.pos)),
+            None => return Err(("unexpected end of file".into(), self.pos)),
+        };
+        Ok(Node {
+            value,
+            start,
+            end: self.pos,
+        })
+    }
+
+    fn parse_literal(&mut self, word: &str, value: NodeValue) -> ScanResult<NodeValue> {
+        if self.text[self.pos..].starts_with(word) {
+            self.pos += word.len();
+            Ok(value)
+        } else {
+            Err(("expected a value".into(), self.pos))
+        }
+    }
+
+    fn parse_number(&mut self) -> ScanResult<NodeValue> {
+        let start = self.pos;
+        let mut integer = true;
+        while let Some(&b) = self.bytes.get(self.pos) {
+            match b {
+                b'0'..=b'9' | b'-' | b'+' => {}
+                b'.' | b'e' | b'E' => integer = false,
+                _ => break,
+            }
+            self.pos += 1;
+        }
+        let value = self.text[start..self.pos]
+            .parse::<f64>()
+            .map_err(|_| ("invalid number".to_string(), start))?;
+        Ok(NodeValue::Number { value, integer })
+    }
+
+    fn parse_string(&mut self) -> ScanResult<String> {
+        let start = self.pos;
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let Some(ch) = self.text[self.pos..].chars().next() else {
+                return Err(("unterminated string".into(), start));
+            };
+            self.pos += ch.len_utf8();
+            match ch {
+                '"' => return Ok(out),
+                '\\' => {
+                    let Some(esc) = self.text[self.pos..].chars().next() else {
+                        return Err(("unterminated string".into(), start));
+                    };
+                    self.pos += esc.len_utf8();
+                    match esc {
+                        'n' => out.push('\n'),
+                        't' => out.push('\t'),
+                        'r' => out.push('\r'),
+                        'b' => out.push('\u{8}'),
+                        'f' => out.push('\u{c}'),
+                        'u' => {
+                            let hex = self
+                                .text
+                                .get(self.pos..self.pos + 4)
+                                .ok_or_else(|| ("invalid unicode escape".to_string(), self.pos))?;
+                            let code = u32::from_str_radix(hex, 16)
+                                .map_err(|_| ("invalid unicode escape".to_string(), self.pos))?;
+                            self.pos += 4;
+                            out.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+                        }
+                        other => out.push(other),
+                    }
+                }
+                '\n' => return Err(("unterminated string".into(), start)),
+                other => out.push(other),
+            }
+        }
+    }
+
+    fn parse_array(&mut self) -> ScanResult<NodeValue> {
+        self.pos += 1;
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) == Some(&b']') {
+            self.pos += 1;
+            return Ok(NodeValue::Array(items));
+        }
+        loop {
+            items.push(self.parse_value()?);
+            self.skip_whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(NodeValue::Array(items));
+                }
+                _ => return Err(("expected ',' or ']'".into(), self.pos)),
+            }
+        }
+    }
+
+    fn parse_object(&mut self) -> ScanResult<NodeValue> {
+        self.pos += 1;
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if self.bytes.get(self.pos) == Some(&b'}') {
+            self.pos += 1;
+            return Ok(NodeValue::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            if self.bytes.get(self.pos) != Some(&b'"') {
+                return Err(("expected a quoted key".into(), self.pos));
+            }
+            let key_start = self.pos;
+            let key = self.parse_string()?;
+            let key_end = self.pos;
+            self.expect(b':')?;
+            let value = self.parse_value()?;
+            members.push(Member {
+                key,
+                key_start,
+                key_end,
+                value,
+            });
+            self.skip_whitespace();
+            match self.bytes.get(self.pos) {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(NodeValue::Object(members));
+                }
+                _ => return Err(("expected ',' or '}'".into(), self.pos)),
+            }
+        }
+    }
+}
+
+// ── Schema Validation ───────────────────────────────────────────────────────
+
+/// Checks a [`Node`] against the subset of JSON Schema emitted by `schemars`.
+struct Validator<'a> {
+    root: &'a JsonValue,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator<'_> {
+    fn check(&mut self, schema: &JsonValue, node: &Node, path: &str) {
+        let JsonValue::Object(schema) = schema else {
+            return;
+        };
+
+        if let Some(target) = schema
+            .get("$ref")
+            .and_then(JsonValue::as_str)
+            .and_then(|r| r.strip_prefix('#'))
+            .and_then(|pointer| self.root.pointer(pointer))
+        {
+            self.check(target, node, path);
+        }
+
+        if let Some(JsonValue::Array(options)) = schema.get("anyOf").or_else(|| schema.get("oneOf"))
+        {
+            self.check_alternatives(options, node, path);
+        }
+
+        if let Some(ty) = schema.get("type") {
+            let allowed: Vec<&str> = match ty {
+                JsonValue::String(s) => vec![s.as_str()],
+                JsonValue::Array(list) => list.iter().filter_map(JsonValue::as_str).collect(),
+                _ => Vec::new(),
+            };
+            if !allowed.is_empty() && !allowed.iter().any(|t| node.matches_type(t)) {
+                self.error(
+                    node.start,
+                    node.end,
+                    format!(
+                        "{} should be {}, found {}",
+                        describe_path(path),
+                        describe_types(&allowed),
+                        node.kind()
+                    ),
+                );
+                return;
+            }
+        }
+
+        if let Some(JsonValue::Array(values)) = schema.get("enum")
+            && !values.contains(&node.to_json())
+        {
+            let expected = values
+                .iter()
+                .map(|v| {
+                    format!(
+                        "`{}`",
+                        v.as_str().map_or_else(|| v.to_string(), str::to_string)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            self.error(
+                node.start,
+                node.end,
+                format!("{} must be one of {expected}", describe_path(path)),
+            );
+        }
+
+        if let (Some(min), NodeValue::Number { value, .. }) = (
+            schema.get("minimum").and_then(JsonValue::as_f64),
+            &node.value,
+        ) && *value < min
+        {
+            self.error(
+                node.start,
+                node.end,
+                format!("{} must be at least {min}", describe_path(path)),
+            );
+        }
+
+        match &node.value {
+            NodeValue::Object(members) => self.check_object(schema, node, members, path),
+            NodeValue::Array(items) => {
+                if let Some(item_schema) = schema.get("items") {
+                    for (i, item) in items.iter().enumerate() {
+                        self.check(item_schema, item, &format!("{path}[{i}]"));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check_alternatives(&mut self, options: &[JsonValue], node: &Node, path: &str) {
+        let mut candidates = Vec::new();
+        for option in options {
+            let mut scratch = Validator {
+                root: self.root,
+                diagnostics: Vec::new(),
+            };
+            scratch.check(option, node, path);
+            if scratch.diagnostics.is_empty() {
+                return;
+            }
+            // An alternative of the right type explains the failure better than a type list.
+            if self.accepts_type(option, node) {
+                candidates.push(scratch.diagnostics);
+            }
+        }
+
+        if let Some(best) = candidates.into_iter().min_by_key(Vec::len) {
+            self.diagnostics.extend(best);
+            return;
+        }
+        let types = options
+            .iter()
+            .flat_map(|option| self.declared_types(option))
+            .collect::<Vec<_>>();
+        self.error(
+            node.start,
+            node.end,
+            format!(
+                "{} should be {}, found {}",
+                describe_path(path),
+                describe_types(&types),
+                node.kind()
+            ),
+        );
+    }
+
+    /// Resolves `$ref` chains and returns the schema's `type` list.
+    fn declared_types<'s>(&'s self, schema: &'s JsonValue) -> Vec<&'s str> {
+        let resolved = schema
+            .get("$ref")
+            .and_then(JsonValue::as_str)
+            .and_then(|r| r.strip_prefix('#'))
+            .and_then(|pointer| self.root.pointer(pointer))
+            .unwrap_or(schema);
+        match resolved.get("type") {
+            Some(JsonValue::String(s)) => vec![s.as_str()],
+            Some(JsonValue::Array(list)) => list.iter().filter_map(JsonValue::as_str).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn accepts_type(&self, schema: &JsonValue, node: &Node) -> bool {
+        let types = self.declared_types(schema);
+        types.is_empty() || types.iter().any(|t| node.matches_type(t))
+    }
+
+    fn check_object(
+        &mut self,
+        schema: &serde_json::Map<String, JsonValue>,
+        node: &Node,
+        members: &[Member],
+        path: &str,
+    ) {
+        let properties = schema.get("properties").and_then(JsonValue::as_object);
+        let closed = schema.get("additionalProperties") == Some(&JsonValue::Bool(false));
+
+        let mut seen = HashSet::new();
+        for member in members {
+            // serde rejects repeated fields, so the server would skip the whole file.
+            if !seen.insert(member.key.as_str()) {
+                self.error(
+                    member.key_start,
+                    member.key_end,
+                    format!("Duplicate key `{}`", member.key),
+                );
+            }
+            let child_path = if path.is_empty() {
+                member.key.clone()
+            } else {
+                format!("{path}.{}", member.key)
+            };
+            match properties.and_then(|p| p.get(&member.key)) {
+                Some(prop_schema) => self.check(prop_schema, &member.value, &child_path),
+                None if closed => {
+                    let known = properties
+                        .map(|p| p.keys().map(String::as_str).collect::<Vec<_>>())
+                        .unwrap_or_default();
+                    let hint = suggest_key(&member.key, &known)
+                        .map(|k| format!(" — did you mean `{k}`?"))
+                        .unwrap_or_default();
+                    self.diagnostics.push(Diagnostic {
+                        message: format!("Unknown key `{}` is ignored{hint}", member.key),
+                        start: member.key_start,
+                        end: member.key_end,
+                        severity: Severity::Warning,
+                        code: None,
+                    });
+                }
+                None => {}
+            }
+        }
+
+        if let Some(JsonValue::Array(required)) = schema.get("required") {
+            for key in required.iter().filter_map(JsonValue::as_str) {
+                if !members.iter().any(|m| m.key == key) {
+                    let location = if path.is_empty() {
+                        String::new()
+                    } else {
+                        format!(" in {}", describe_path(path))
+                    };
+                    self.error(
+                        node.start,
+                        node.start + 1,
+                        format!("Missing required key `{key}`{location}"),
+                    );
+                }
+            }
+        }
+    }
+
+    fn error(&mut self, start: usize, end: usize, message: String) {
+        self.diagnostics.push(Diagnostic {
+            message,
+            start,
+            end,
+            severity: Severity::Error,
+            code: None,
+        });
+    }
+}
+
+fn describe_path(path: &str) -> String {
+    if path.is_empty() {
+        "The configuration".to_string()
+    } else {
+        format!("`{path}`")
+    }
+}
+
+fn describe_types(types: &[&str]) -> String {
+    let names = types
+        .iter()
+        .filter(|t| **t != "null" || types.len() == 1)
+        .map(|t| match *t {
+            "integer" => "an integer",
+            "array" => "an array",
+            "object" => "an object",
+            "null" => "null",
+            "string" => "a string",
+            "number" => "a number",
+            "boolean" => "a boolean",
+            other => other,
+        })
+        .collect::<Vec<_>>();
+    names.join(" or ")
+}
+
+/// Finds a known key that differs from `key` only by casing, separators or a small typo.
+pub(crate) fn suggest_key<'k>(key: &str, known: &[&'k str]) -> Option<&'k str> {
+    let normalize = |s: &str| {
+        s.chars()
+            .filter(|c| *c != '_' && *c != '-')
+            .flat_map(char::to_lowercase)
+            .collect::<String>()
+    };
+    let wanted = normalize(key);
+    known
+        .iter()
+        .map(|k| (*k, edit_distance(&wanted, &normalize(k))))
+        .filter(|(_, d)| *d <= 2)
+        .min_by_key(|(_, d)| *d)
+        .map(|(k, _)| k)
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cur = row[j + 1];
+            row[j + 1] = if ca == *cb {
+                prev
+            } else {
+                1 + prev.min(row[j]).min(cur)
+            };
+            prev = cur;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn messages(text: &str) -> Vec<(String, &str)> {
+        validate_config(text)
+            .into_iter()
+            .map(|d| (d.message, &text[d.start..d.end]))
+            .collect()
+    }
+
+    #[test]
+    fn accepts_valid_config() {
+        assert!(messages(r#"{"urls": ["https://example.com/functions.json"]}"#).is_empty());
+    }
+
+    #[test]
+    fn reports_duplicate_keys() {
+        let text = r#"{"urls": [], "urls": []}"#;
+        let diagnostics = validate_config(text);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].message, "Duplicate key `urls`");
+        assert_eq!((diagnostics[0].start, diagnostics[0].end), (13, 19));
+    }
+
+    #[test]
+    fn suggests_known_key_for_unknown_key() {
+        assert_eq!(
+            messages(r#"{"urls": [], "customFunctionsPath": "./functions"}"#),
+            [(
+                "Unknown key `customFunctionsPath` is ignored — did you mean `custom_functions_path`?"
+                    .to_string(),
+                "\"customFunctionsPath\""
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_wrong_types() {
+        assert_eq!(
+            messages(r#"{"urls": "https://example.com"}"#),
+            [(
+                "`urls` should be an array, found string".to_string(),
+                "\"https://example.com\""
+            )]
+        );
+        assert_eq!(
+            messages(r#"{"urls": [1]}"#),
+            [(
+                "`urls[0]` should be a string, found integer".to_string(),
+                "1"
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_missing_urls() {
+        assert_eq!(
+            messages("{}"),
+            [("Missing required key `urls`".to_string(), "{")]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_json() {
+        let diagnostics = validate_config(r#"{"urls": [}"#);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(diagnostics[0].message.starts_with("Invalid JSON"));
+    }
+
+    #[test]
+    fn underlines_whole_character_in_invalid_json() {
+        let messages = messages(r#"{"urls": é}"#);
+        assert_eq!(messages.len(), 1);
+        assert_eq!(messages[0].1, "é");
+    }
+
+    #[test]
+    fn reports_conflicts_at_their_definitions() {
+        let text = r#"{"urls": ["https://a.dev/functions.json", "https://b.dev/functions.json"], "custom_functions": [{"name": "ping"}]}"#;
+        let source = |url: &str| Function {
+            source_url: Some(url.to_string()),
+            ..Function::default()
+        };
+        let custom = Function {
+            category: Some("custom".to_string()),
+            ..Function::default()
+        };
+        let conflicts = [
+            FunctionConflict {
+                name: "$ping".to_string(),
+                kept: Arc::new(custom),
+                shadowed: Arc::new(source("https://a.dev/functions.json")),
+            },
+            FunctionConflict {
+                name: "$send".to_string(),
+                kept: Arc::new(source("https://b.dev/functions.json")),
+                shadowed: Arc::new(source("https://a.dev/functions.json")),
+            },
+        ];
+        let diagnostics = conflict_diagnostics(text, &conflicts);
+        let spans: Vec<_> = diagnostics.iter().map(|d| &text[d.start..d.end]).collect();
+        assert_eq!(spans, ["\"ping\"", "\"https://b.dev/functions.json\""]);
+        assert!(diagnostics[0].message.contains("overrides the built-in"));
+        assert!(diagnostics[1].message.contains("`$send`"));
+    }
+
+    #[test]
+    fn schema_hides_custom_function_locations() {
+        let schema = config_schema().to_string();
+        assert!(schema.contains("\"brackets\""));
+        assert!(!schema.contains("\"column\""));
+    }
+}
@@ -0,0 +1,253 @@
+//! Structural checks for conditional and loop functions.
+//!
+//! Calls are related by their spans: a call nested in another call's argument is
+//! its child, and calls sharing the same argument (or top-level code block) are
+//! siblings. Every diagnostic carries a code so that it can be suppressed from
+//! `forgeconfig.json`.
+
+use std::collections::HashMap;
+
+use crate::condition::{condition_args, parse_condition};
+use crate::parser::{Diagnostic, ParsedFunction, Severity, code_blocks};
+
+/// Code of a condition argument that is not a well-formed comparison.
+pub const INVALID_CONDITION: &str = "invalid-condition";
+/// Code of an `$elseif`/`$else` that does not follow an `$if`.
+pub const ORPHAN_ELSE: &str = "orphan-else";
+/// Code of a `$break`/`$continue` outside of any loop.
+pub const LOOP_CONTROL_OUTSIDE_LOOP: &str = "loop-control-outside-loop";
+
+/// Functions whose code arguments run repeatedly.
+///
+/// Metadata does not mark iterating functions, so a call missing here only
+/// produces a warning.
+const LOOP_FUNCTIONS: [&str; 10] = [
+    "arrayEvery",
+    "arrayFilter",
+    "arrayFind",
+    "arrayFindIndex",
+    "arrayForEach",
+    "arrayMap",
+    "arraySome",
+    "jsonForEach",
+    "loop",
+    "while",
+];
+
+/// Where a call sits: inside an argument of another call, or directly in a code block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Container {
+    Argument { parent: usize, arg: usize },
+    Block(usize),
+}
+
+/// Runs every control-flow check over the parsed calls of `text`.
+///
+/// `functions` must be ordered like `ParseResult::functions`: by start, outer calls first.
+pub fn check_control_flow(text: &str, functions: &[ParsedFunction]) -> Vec<Diagnostic> {
+    let containers = containers(text, functions);
+
+    let mut diagnostics = Vec::new();
+    check_conditions(text, functions, &mut diagnostics);
+    check_else_chains(functions, &containers, &mut diagnostics);
+    check_loop_control(functions, &containers, &mut diagnostics);
+    diagnostics
+}
+
+fn is_named(func: &ParsedFunction, names: &[&str]) -> bool {
+    names.iter().any(|n| n.eq_ignore_ascii_case(&func.name))
+}
+
+/// Finds the innermost argument containing each call, walking the sorted calls once
+/// with a stack of the calls still open.
+fn containers(text: &str, functions: &[ParsedFunction]) -> Vec<Container> {
+    let blocks = code_blocks(text);
+    let mut block = 0;
+    let mut open: Vec<usize> = Vec::new();
+    let mut containers = Vec::with_capacity(functions.len());
+    for (index, func) in functions.iter().enumerate() {
+        let span = func.span;
+        while open
+            .last()
+            .is_some_and(|&parent| functions[parent].span.1 <= span.0)
+        {
+            open.pop();
+        }
+        let argument = open.last().and_then(|&parent| {
+            let args = functions[parent].args.as_ref()?;
+            let arg = args
+                .iter()
+                .position(|(_, arg)| arg.0 <= span.0 && span.1 <= arg.1)?;
+            Some(Container::Argument { parent, arg })
+        });
+        containers.push(argument.unwrap_or_else(|| {
+            while blocks.get(block).is_some_and(|&(_, end)| end < span.0) {
+                block += 1;
+            }
+            Container::Block(block.min(blocks.len().saturating_sub(1)))
+        }));
+        open.push(index);
+    }
+    containers
+}
+
+/// Reports condition arguments that do not parse as comparisons or boolean values.
+fn check_conditions(text: &str, functions: &[ParsedFunction], out: &mut Vec<Diagnostic>) {
+    for (func, span) in condition_args(functions) {
+        let condition = text.get(span.0..span.1).unwrap_or_default();
+        let (message, span) = if condition.trim().is_empty() {
+            (format!("${} has an empty condition", func.name), span)
+        } else {
+            match parse_condition(condition, span.0) {
+                Ok(_) => continue,
+                Err(e) => (
+                    format!("Invalid ${} condition: {}", func.name, e.message),
+                    e.span,
+                ),
+            }
+        };
+        out.push(Diagnostic {
+            message,
+            start: span.0,
+            end: span.1,
+            severity: Severity::Warning,
+            code: Some(INVALID_CONDITION.to_string()),
+        });
+    }
+}
+
+/// Reports `$elseif`/`$else` calls whose previous sibling is not `$if` or `$elseif`.
+fn check_else_chains(
+    functions: &[ParsedFunction],
+    containers: &[Container],
+    out: &mut Vec<Diagnostic>,
+) {
+    // Siblings never overlap, so the last call seen in a container is the previous sibling.
+    let mut last_in: HashMap<Container, usize> = HashMap::new();
+    for (i, func) in functions.iter().enumerate() {
+        let previous = last_in.insert(containers[i], i);
+        if !is_named(func, &["elseif", "else"]) {
+            continue;
+        }
+        if previous.is_some_and(|p| is_named(&functions[p], &["if", "elseif"])) {
+            continue;
+        }
+        out.push(Diagnostic {
+            message: format!("${} must follow $if or $elseif", func.name),
+            start: func.span.0,
+            end: func.span.0 + func.matched.len(),
+            severity: Severity::Error,
+            code: Some(ORPHAN_ELSE.to_string()),
+        });
+    }
+}
+
+/// Reports `$break`/`$continue` calls that are not nested in a loop function.
+fn check_loop_control(
+    functions: &[ParsedFunction],
+    containers: &[Container],
+    out: &mut Vec<Diagnostic>,
+) {
+    for (i, func) in functions.iter().enumerate() {
+        if !is_named(func, &["break", "continue"]) {
+            continue;
+        }
+        let mut container = containers[i];
+        let mut in_loop = false;
+        while let Container::Argument { parent, .. } = container {
+            if is_named(&functions[parent], &LOOP_FUNCTIONS) {
+                in_loop = true;
+                break;
+            }
+            container = containers[parent];
+        }
+        if in_loop {
+            continue;
+        }
+        out.push(Diagnostic {
+            message: format!("${} can only be used inside a loop", func.name),
+            start: func.span.0,
+            end: func.span.0 + func.matched.len(),
+            severity: Severity::Warning,
+            code: Some(LOOP_CONTROL_OUTSIDE_LOOP.to_string()),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::test_manager;
+    use crate::parser::ForgeScriptParser;
+
+    fn codes(code: &str) -> Vec<(String, Severity)> {
+        let manager = test_manager(&[
+            ("if", &["?condition", "code"]),
+            ("elseif", &["?condition", "code"]),
+            ("else", &["code"]),
+            ("loop", &["times", "code"]),
+            ("arrayMap", &["name", "variable", "code"]),
+            ("log", &["message"]),
+            ("break", &[]),
+            ("continue", &[]),
+        ]);
+        let parsed = ForgeScriptParser::new(manager, code).parse();
+        check_control_flow(code, &parsed.functions)
+            .into_iter()
+            .map(|d| (d.code.unwrap_or_default(), d.severity))
+            .collect()
+    }
+
+    #[test]
+    fn reports_else_without_if() {
+        assert_eq!(
+            codes("code: `$else[no]`"),
+            [(ORPHAN_ELSE.to_string(), Severity::Error)]
+        );
+        assert_eq!(
+            codes("code: `$log[a]$elseif[1==1;no]`"),
+            [(ORPHAN_ELSE.to_string(), Severity::Error)]
+        );
+        assert!(codes("code: `$if[1==1;yes]$elseif[1==2;maybe]$else[no]`").is_empty());
+    }
+
+    #[test]
+    fn else_chains_are_checked_per_argument() {
+        assert_eq!(
+            codes("code: `$if[1==1;$else[no]]`"),
+            [(ORPHAN_ELSE.to_string(), Severity::Error)]
+        );
+        assert!(codes("code: `$if[1==1;$if[2==2;a]$else[b]]$else[c]`").is_empty());
+    }
+
+    #[test]
+    fn allows_loop_control_inside_loops() {
+        assert!(codes("code: `$loop[3;$if[1==1;$break]]`").is_empty());
+        assert!(codes("code: `$arrayMap[list;item;$continue]`").is_empty());
+    }
+
+    #[test]
+    fn warns_about_loop_control_outside_loops() {
+        assert_eq!(
+            codes("code: `$break`"),
+            [(LOOP_CONTROL_OUTSIDE_LOOP.to_string(), Severity::Warning)]
+        );
+        assert_eq!(
+            codes("code: `$loop[3;a]$if[1==1;$continue]`"),
+            [(LOOP_CONTROL_OUTSIDE_LOOP.to_string(), Severity::Warning)]
+        );
+    }
+
+    #[test]
+    fn reports_invalid_conditions() {
+        assert_eq!(
+            codes("code: `$if[;yes]`"),
+            [(INVALID_CONDITION.to_string(), Severity::Warning)]
+        );
+        assert_eq!(
+            codes("code: `$if[1==;yes]`"),
+            [(INVALID_CONDITION.to_string(), Severity::Warning)]
+        );
+        assert!(codes("code: `$if[1==1;yes]`").is_empty());
+    }
+}
@@ -0,0 +1,1002 @@
+//! Extraction of custom function definitions from JS/TS source files.
+//!
+//! A small token-level scanner skips comments, string/template literals and
+//! regular expressions, then reads the object literals passed to
+//! `new NativeFunction({...})` / `new ForgeFunction({...})` or exported via
+//! `export default` / `module.exports`. Only literal values are evaluated;
+//! everything else (functions, calls, computed keys) is skipped.
+//!
+//! The same scanner reads the event `type` of command objects and the options
+//! passed to `new ForgeClient({...})`.
+
+use std::collections::{HashMap, HashSet};
+
+use serde_json::Value as JsonValue;
+
+use crate::parser::{Diagnostic, Severity};
+use crate::utils::{CustomFunction, offset_to_position_raw};
+
+/// Classes whose constructor takes a function definition object.
+const DEFINITION_CLASSES: [&str; 2] = ["NativeFunction", "ForgeFunction"];
+
+/// A function definition object found in a source file.
+#[derive(Debug, Clone)]
+pub struct ExtractedFunction {
+    /// The definition, with `path`, `line` and `column` pointing at its name.
+    pub function: CustomFunction,
+    /// Byte span of the definition object literal.
+    pub span: (usize, usize),
+    /// Byte span of the name string's contents; `None` when the definition has no name.
+    pub name_span: Option<(usize, usize)>,
+    pub params: Vec<ExtractedParam>,
+}
+
+/// An argument declared in a definition's `params`/`args` array.
+#[derive(Debug, Clone)]
+pub struct ExtractedParam {
+    pub name: Option<String>,
+    pub span: (usize, usize),
+    /// Declared type with any `ArgType.` prefix removed, and where it was written.
+    pub type_name: Option<(String, (usize, usize))>,
+    pub required: bool,
+    pub rest: bool,
+}
+
+/// Finds every custom function definition in a JS/TS file.
+pub fn extract_custom_functions(source: &str, file_path: &str) -> Vec<ExtractedFunction> {
+    let tokens = Tokenizer::new(source).tokenize();
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+    };
+
+    let mut objects = Vec::new();
+    for start in definition_starts(&tokens) {
+        parser.pos = start;
+        match parser.parse_value() {
+            Value::Object(obj) => objects.push(obj),
+            Value::Array(items) => objects.extend(items.into_iter().filter_map(|v| match v {
+                Value::Object(obj) => Some(obj),
+                _ => None,
+            })),
+            _ => {}
+        }
+    }
+
+    objects
+        .into_iter()
+        .map(|obj| to_custom_function(obj, source, file_path))
+        .collect()
+}
+
+/// Returns the token indices of every definition object (or array of objects).
+fn definition_starts(tokens: &[Token]) -> Vec<usize> {
+    let is = |i: usize, kind: &TokenKind| tokens.get(i).is_some_and(|t| &t.kind == kind);
+    let is_ident = |i: usize, name: &str| {
+        tokens
+            .get(i)
+            .is_some_and(|t| matches!(&t.kind, TokenKind::Ident(n) if n == name))
+    };
+    let opens_literal = |i: usize| is(i, &TokenKind::Punct('{')) || is(i, &TokenKind::Punct('['));
+
+    let mut starts = Vec::new();
+    for i in 0..tokens.len() {
+        // new NativeFunction({ ... })
+        if is_ident(i, "new")
+            && DEFINITION_CLASSES.iter().any(|c| is_ident(i + 1, c))
+            && is(i + 2, &TokenKind::Punct('('))
+            && is(i + 3, &TokenKind::Punct('{'))
+        {
+            starts.push(i + 3);
+        }
+        // export default { ... } / export default [ ... ]
+        if is_ident(i, "export") && is_ident(i + 1, "default") && opens_literal(i + 2) {
+            starts.push(i + 2);
+        }
+        // module.exports = { ... } / module.exports = [ ... ]
+        if is_ident(i, "module")
+            && is(i + 1, &TokenKind::Punct('.'))
+            && is_ident(i + 2, "exports")
+            && is(i + 3, &TokenKind::Punct('='))
+            && opens_literal(i + 4)
+        {
+            starts.push(i + 4);
+        }
+    }
+    starts
+}
+
+// ── Tokenizer ───────────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Ident(String),
+    /// A string or template literal with its cooked contents.
+    Str(String),
+    Number(String),
+    Punct(char),
+}
+
+#[derive(Debug, Clone)]
+struct Token {
+    kind: TokenKind,
+    start: usize,
+    end: usize,
+}
+
+struct Tokenizer<'a> {
+    src: &'a str,
+    pos: usize,
+    /// Whether a `/` at the current position would start a regular expression.
+    regex_allowed: bool,
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            pos: 0,
+            regex_allowed: true,
+        }
+    }
+
+    fn tokenize(mut self) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        while let Some(token) = self.next_token() {
+            tokens.push(token);
+        }
+        tokens
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<char> {
+        self.src[self.pos..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ch.len_utf8();
+        Some(ch)
+    }
+
+    /// Skips whitespace and comments.
+    fn skip_trivia(&mut self) {
+        loop {
+            match (self.peek(), self.peek_at(1)) {
+                (Some(c), _) if c.is_whitespace() => {
+                    self.bump();
+                }
+                (Some('/'), Some('/')) => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                }
+                (Some('/'), Some('*')) => {
+                    self.pos += 2;
+                    match self.src[self.pos..].find("*/") {
+                        Some(end) => self.pos += end + 2,
+                        None => self.pos = self.src.len(),
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Option<Token> {
+        self.skip_trivia();
+        let start = self.pos;
+        let ch = self.peek()?;
+
+        let kind = if ch == '"' || ch == '\'' {
+            TokenKind::Str(self.scan_quoted(ch))
+        } else if ch == '`' {
+            TokenKind::Str(self.scan_template())
+        } else if ch.is_ascii_digit()
+            || (ch == '.' && self.peek_at(1).is_some_and(|c| c.is_ascii_digit()))
+        {
+            while self
+                .peek()
+                .is_some_and(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_')
+            {
+                self.bump();
+            }
+            TokenKind::Number(self.src[start..self.pos].to_string())
+        } else if is_ident_start(ch) {
+            while self.peek().is_some_and(is_ident_part) {
+                self.bump();
+            }
+            TokenKind::Ident(self.src[start..self.pos].to_string())
+        } else if ch == '/' && self.regex_allowed {
+            self.scan_regex();
+            TokenKind::Str(self.src[start..self.pos].to_string())
+        } else {
+            self.bump();
+            TokenKind::Punct(ch)
+        };
+
+        self.regex_allowed = match &kind {
+            TokenKind::Punct(c) => !matches!(c, ')' | ']' | '}'),
+            TokenKind::Ident(word) => matches!(
+                word.as_str(),
+                "return" | "typeof" | "case" | "in" | "of" | "new" | "delete" | "void" | "throw"
+            ),
+            _ => false,
+        };
+        Some(Token {
+            kind,
+            start,
+            end: self.pos,
+        })
+    }
+
+    fn scan_quoted(&mut self, quote: char) -> String {
+        self.bump();
+        let mut out = String::new();
+        while let Some(ch) = self.bump() {
+            match ch {
+                c if c == quote => break,
+                '\\' => {
+                    if let Some(escaped) = self.bump() {
+                        out.push(unescape(escaped));
+                    }
+                }
+                '\n' => break,
+                c => out.push(c),
+            }
+        }
+        out
+    }
+
+    /// Scans a template literal; `${...}` substitutions are kept verbatim.
+    fn scan_template(&mut self) -> String {
+        self.bump();
+        let mut out = String::new();
+        while let Some(ch) = self.bump() {
+            match ch {
+                '`' => break,
+                '\\' => {
+                    if let Some(escaped) = self.bump() {
+                        out.push(unescape(escaped));
+                    }
+                }
+                '$' if self.peek() == Some('{') => {
+                    let expr_start = self.pos - 1;
+                    self.bump();
+                    self.skip_substitution();
+                    out.push_str(&self.src[expr_start..self.pos]);
+                }
+                c => out.push(c),
+            }
+        }
+        out
+    }
+
+    /// Skips the body of a `${...}` substitution, including nested literals.
+    fn skip_substitution(&mut self) {
+        let mut depth = 1usize;
+        self.regex_allowed = true;
+        while let Some(token) = self.next_token() {
+            match token.kind {
+                TokenKind::Punct('{') => depth += 1,
+                TokenKind::Punct('}') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn scan_regex(&mut self) {
+        self.bump();
+        let mut in_class = false;
+        while let Some(ch) = self.bump() {
+            match ch {
+                '\\' => {
+                    self.bump();
+                }
+                '[' => in_class = true,
+                ']' => in_class = false,
+                '/' if !in_class => break,
+                '\n' => return,
+                _ => {}
+            }
+        }
+        while self.peek().is_some_and(is_ident_part) {
+            self.bump();
+        }
+    }
+}
+
+fn is_ident_start(ch: char) -> bool {
+    ch.is_alphabetic() || ch == '_' || ch == '$'
+}
+
+fn is_ident_part(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == '$'
+}
+
+fn unescape(ch: char) -> char {
+    match ch {
+        'n' => '\n',
+        't' => '\t',
+        'r' => '\r',
+        '0' => '\0',
+        other => other,
+    }
+}
+
+// ── Literal Parser ──────────────────────────────────────────────────────────
+
+#[derive(Debug)]
+enum Value {
+    Str(String, (usize, usize)),
+    Bool(bool),
+    /// A dotted identifier such as `ArgType.Number`.
+    Path(String, (usize, usize)),
+    Array(Vec<Value>),
+    Object(ObjectLit),
+    /// Anything that is not a plain literal.
+    Other,
+}
+
+#[derive(Debug)]
+struct ObjectLit {
+    members: Vec<(String, Value)>,
+    span: (usize, usize),
+}
+
+impl ObjectLit {
+    fn get(&self, key: &str) -> Option<&Value> {
+        self.members.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+}
+
+struct Parser<'t> {
+    tokens: &'t [Token],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&TokenKind> {
+        self.tokens.get(self.pos).map(|t| &t.kind)
+    }
+
+    fn is_punct(&self, ch: char) -> bool {
+        self.peek() == Some(&TokenKind::Punct(ch))
+    }
+
+    fn at_value_end(&self) -> bool {
+        matches!(
+            self.peek(),
+            None | Some(TokenKind::Punct(',' | '}' | ']' | ')' | ';'))
+        )
+    }
+
+    fn parse_value(&mut self) -> Value {
+        let Some(token) = self.tokens.get(self.pos) else {
+            return Value::Other;
+        };
+        let value = match &token.kind {
+            TokenKind::Punct('{') => return self.parse_object(),
+            TokenKind::Punct('[') => return self.parse_array(),
+            TokenKind::Str(s) => {
+                self.pos += 1;
+                Value::Str(s.clone(), (token.start + 1, token.end.saturating_sub(1)))
+            }
+            TokenKind::Ident(word) if word == "true" || word == "false" => {
+                self.pos += 1;
+                Value::Bool(word == "true")
+            }
+            TokenKind::Ident(word) if word != "new" && word != "function" && word != "async" => {
+                self.pos += 1;
+                let mut path = word.clone();
+                while self.is_punct('.')
+                    && let Some(TokenKind::Ident(next)) =
+                        self.tokens.get(self.pos + 1).map(|t| &t.kind)
+                {
+                    path.push('.');
+                    path.push_str(next);
+                    self.pos += 2;
+                }
+                Value::Path(path, (token.start, self.tokens[self.pos - 1].end))
+            }
+            _ => {
+                self.skip_expression();
+                return Value::Other;
+            }
+        };
+
+        if self.at_value_end() {
+            return value;
+        }
+        // `"x" as const` and `ArgType.Number satisfies T` keep their literal value.
+        let keeps_value =
+            matches!(self.peek(), Some(TokenKind::Ident(w)) if w == "as" || w == "satisfies");
+        self.skip_expression();
+        if keeps_value { value } else { Value::Other }
+    }
+
+    fn parse_array(&mut self) -> Value {
+        self.pos += 1;
+        let mut items = Vec::new();
+        while self.peek().is_some() {
+            if self.is_punct(']') {
+                self.pos += 1;
+                break;
+            }
+            if self.is_punct(',') {
+                self.pos += 1;
+                continue;
+            }
+            let before = self.pos;
+            items.push(self.parse_value());
+            if self.pos == before {
+                self.pos += 1;
+            }
+        }
+        Value::Array(items)
+    }
+
+    fn parse_object(&mut self) -> Value {
+        let start = self.tokens[self.pos].start;
+        self.pos += 1;
+        let mut members = Vec::new();
+
+        let tokens = self.tokens;
+        while let Some(token) = tokens.get(self.pos) {
+            match token.kind {
+                TokenKind::Punct('}') => {
+                    self.pos += 1;
+                    break;
+                }
+                TokenKind::Punct(',') => {
+                    self.pos += 1;
+                    continue;
+                }
+                _ => {}
+            }
+
+            let before = self.pos;
+            if let Some(member) = self.parse_member() {
+                members.push(member);
+            }
+            if !self.is_punct(',') && !self.is_punct('}') {
+                self.skip_expression();
+            }
+            if self.pos == before {
+                self.pos += 1;
+            }
+        }
+
+        let end = self.tokens[self.pos.saturating_sub(1)].end;
+        Value::Object(ObjectLit {
+            members,
+            span: (start, end),
+        })
+    }
+
+    /// Parses one `key: value` pair; methods, spreads and computed keys yield `None`.
+    fn parse_member(&mut self) -> Option<(String, Value)> {
+        // Skip `async`, `get`, `set` and generator markers in front of method keys.
+        while let Some(TokenKind::Ident(word)) = self.peek()
+            && matches!(word.as_str(), "async" | "get" | "set" | "static")
+            && matches!(
+                self.tokens.get(self.pos + 1).map(|t| &t.kind),
+                Some(TokenKind::Ident(_) | TokenKind::Str(_) | TokenKind::Punct('[' | '*'))
+            )
+        {
+            self.pos += 1;
+        }
+        if self.is_punct('*') {
+            self.pos += 1;
+        }
+
+        let key = match self.peek()? {
+            TokenKind::Ident(k) | TokenKind::Str(k) | TokenKind::Number(k) => Some(k.clone()),
+            TokenKind::Punct('[') => {
+                self.skip_balanced();
+                None
+            }
+            _ => {
+                self.skip_expression();
+                return None;
+            }
+        };
+        if key.is_some() {
+            self.pos += 1;
+        }
+
+        match self.peek() {
+            Some(TokenKind::Punct(':')) => {
+                self.pos += 1;
+                let value = self.parse_value();
+                key.map(|k| (k, value))
+            }
+            Some(TokenKind::Punct('(' | '<')) => {
+                // Method: skip parameters, return type annotation and body.
+                while self.peek().is_some() && !self.is_punct('{') {
+                    if self.is_punct('(') {
+                        self.skip_balanced();
+                    } else {
+                        self.pos += 1;
+                    }
+                }
+                self.skip_balanced();
+                None
+            }
+            _ => key.map(|k| (k, Value::Other)),
+        }
+    }
+
+    /// Skips a bracketed group starting at the current `(`, `[` or `{`.
+    fn skip_balanced(&mut self) {
+        let mut depth = 0usize;
+        let tokens = self.tokens;
+        while let Some(token) = tokens.get(self.pos) {
+            self.pos += 1;
+            match token.kind {
+                TokenKind::Punct('(' | '[' | '{') => depth += 1,
+                TokenKind::Punct(')' | ']' | '}') => {
+                    depth = depth.saturating_sub(1);
+                    if depth == 0 {
+                        return;
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Skips tokens up to the next `,` or closing bracket at the current nesting level.
+    fn skip_expression(&mut self) {
+        while let Some(kind) = self.peek() {
+            match kind {
+                TokenKind::Punct('(' | '[' | '{') => self.skip_balanced(),
+                TokenKind::Punct(',' | ')' | ']' | '}' | ';') => return,
+                _ => self.pos += 1,
+            }
+        }
+    }
+}
+
+// ── Conversion ──────────────────────────────────────────────────────────────
+
+fn to_custom_function(obj: ObjectLit, source: &str, file_path: &str) -> ExtractedFunction {
+    let (name, name_span) = match obj.get("name") {
+        Some(Value::Str(name, span)) => (name.clone(), Some(*span)),
+        _ => (String::new(), None),
+    };
+    let (line, column) = offset_to_position_raw(source, name_span.map_or(obj.span.0, |s| s.0));
+
+    let params = obj
+        .get("params")
+        .or_else(|| obj.get("args"))
+        .and_then(|value| match value {
+            Value::Array(items) => Some(JsonValue::Array(
+                items.iter().filter_map(param_to_json).collect(),
+            )),
+            _ => None,
+        });
+
+    let function = CustomFunction {
+        name,
+        description: string_of(obj.get("description")),
+        params,
+        brackets: match obj.get("brackets") {
+            Some(Value::Bool(b)) => Some(*b),
+            _ => None,
+        },
+        alias: obj
+            .get("aliases")
+            .or_else(|| obj.get("alias"))
+            .and_then(strings_of),
+        path: Some(file_path.to_string()),
+        line: Some(line),
+        column: Some(column),
+        output: obj.get("output").and_then(strings_of),
+    };
+    let params = match obj.get("params").or_else(|| obj.get("args")) {
+        Some(Value::Array(items)) => items.iter().filter_map(extracted_param).collect(),
+        _ => Vec::new(),
+    };
+    ExtractedFunction {
+        function,
+        span: obj.span,
+        name_span,
+        params,
+    }
+}
+
+fn extracted_param(value: &Value) -> Option<ExtractedParam> {
+    match value {
+        // Bare names are registered as required string arguments.
+        Value::Str(name, span) => Some(ExtractedParam {
+            name: Some(name.clone()),
+            span: *span,
+            type_name: None,
+            required: true,
+            rest: false,
+        }),
+        Value::Object(obj) => Some(ExtractedParam {
+            name: string_of(obj.get("name")),
+            span: obj.span,
+            type_name: match obj.get("type") {
+                Some(Value::Str(s, span) | Value::Path(s, span)) => {
+                    Some((s.strip_prefix("ArgType.").unwrap_or(s).to_string(), *span))
+                }
+                _ => None,
+            },
+            required: matches!(obj.get("required"), Some(Value::Bool(true))),
+            rest: matches!(obj.get("rest"), Some(Value::Bool(true))),
+        }),
+        _ => None,
+    }
+}
+
+fn param_to_json(value: &Value) -> Option<JsonValue> {
+    let obj = match value {
+        Value::Str(name, _) => return Some(JsonValue::String(name.clone())),
+        Value::Object(obj) => obj,
+        _ => return None,
+    };
+    let Some(Value::Str(name, _)) = obj.get("name") else {
+        return None;
+    };
+
+    let mut map = serde_json::Map::new();
+    map.insert("name".into(), JsonValue::String(name.clone()));
+    if let Some(description) = string_of(obj.get("description")) {
+        map.insert("description".into(), JsonValue::String(description));
+    }
+    for flag in ["required", "rest"] {
+        if let Some(Value::Bool(b)) = obj.get(flag) {
+            map.insert(flag.into(), JsonValue::Bool(*b));
+        }
+    }
+    let ty = type_name(obj.get("type")).unwrap_or_else(|| "String".to_string());
+    map.insert("type".into(), JsonValue::String(ty));
+    match obj.get("enum") {
+        Some(Value::Path(path, _)) => {
+            let enum_name = path.rsplit('.').next().unwrap_or(path);
+            map.insert("enum_name".into(), JsonValue::String(enum_name.to_string()));
+        }
+        Some(values @ Value::Array(_)) => {
+            if let Some(values) = strings_of(values) {
+                map.insert("arg_enum".into(), values.into());
+            }
+        }
+        _ => {}
+    }
+    Some(JsonValue::Object(map))
+}
+
+/// Reads a type written as `ArgType.Number`, `"Number"` or `Number`.
+fn type_name(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::Str(s, _) | Value::Path(s, _) => {
+            Some(s.strip_prefix("ArgType.").unwrap_or(s).to_string())
+        }
+        _ => None,
+    }
+}
+
+fn string_of(value: Option<&Value>) -> Option<String> {
+    match value? {
+        Value::Str(s, _) => Some(s.clone()),
+        _ => None,
+    }
+}
+
+/// Reads a single string/type or an array of them.
+fn strings_of(value: &Value) -> Option<Vec<String>> {
+    let values = match value {
+        Value::Array(items) => items.iter().filter_map(|v| type_name(Some(v))).collect(),
+        other => vec![type_name(Some(other))?],
+    };
+    Some(values)
+}
+
+// ── Definition Checks ───────────────────────────────────────────────────────
+
+/// Reports malformed definitions: missing or duplicate names, unknown argument
+/// types and required arguments that follow a rest argument.
+///
+/// Type checks are skipped when `known_types` is empty (no metadata loaded yet).
+pub fn check_definitions(
+    source: &str,
+    functions: &[ExtractedFunction],
+    known_types: &HashSet<String>,
+) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    let mut seen: HashMap<String, usize> = HashMap::new();
+
+    for func in functions {
+        match func.name_span {
+            None => diagnostics.push(Diagnostic {
+                message: "Custom function definition has no `name` and is ignored".to_string(),
+                start: func.span.0,
+                end: func.span.0 + 1,
+                severity: Severity::Error,
+                code: None,
+            }),
+            Some((start, end)) => {
+                let key = normalized_name(&func.function.name);
+                if let Some(first) = seen.get(&key) {
+                    let (line, _) = offset_to_position_raw(source, *first);
+                    diagnostics.push(Diagnostic {
+                        message: format!(
+                            "Duplicate custom function `{key}` (first defined on line {})",
+                            line + 1
+                        ),
+                        start,
+                        end,
+                        severity: Severity::Warning,
+                        code: None,
+                    });
+                } else {
+                    seen.insert(key, start);
+                }
+            }
+        }
+
+        let mut rest_param: Option<&ExtractedParam> = None;
+        for param in &func.params {
+            let label = param.name.as_deref().unwrap_or("?");
+            if let Some((ty, (start, end))) = &param.type_name
+                && !known_types.is_empty()
+                && !known_types.contains(ty)
+            {
+                diagnostics.push(Diagnostic {
+                    message: format!("Unknown argument type `{ty}` for `{label}`"),
+                    start: *start,
+                    end: *end,
+                    severity: Severity::Warning,
+                    code: None,
+                });
+            }
+            if let Some(rest) = rest_param
+                && param.required
+            {
+                diagnostics.push(Diagnostic {
+                    message: format!(
+                        "Required argument `{label}` cannot follow rest argument `{}`",
+                        rest.name.as_deref().unwrap_or("?")
+                    ),
+                    start: param.span.0,
+                    end: param.span.1,
+                    severity: Severity::Error,
+                    code: None,
+                });
+            }
+            if param.rest {
+                rest_param.get_or_insert(param);
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Returns a function name with its leading `$`, as it is registered.
+pub fn normalized_name(name: &str) -> String {
+    if name.starts_with('$') {
+        name.to_string()
+    } else {
+        format!("${name}")
+    }
+}
+
+// ── Commands and Client Setup ───────────────────────────────────────────────
+
+/// The `type:` of a command object, i.e. the event it runs on.
+#[derive(Debug, Clone)]
+pub struct CommandType {
+    pub event: String,
+    /// Byte span of the string's contents.
+    pub span: (usize, usize),
+}
+
+/// Finds the `type:` string of every command object (an object literal with `code`).
+pub fn extract_command_types(source: &str) -> Vec<CommandType> {
+    let tokens = Tokenizer::new(source).tokenize();
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: 0,
+    };
+
+    // Objects nested in function bodies are not reached by `parse_value`, so every
+    // `{` is tried on its own.
+    let mut found = Vec::new();
+    for (start, token) in tokens.iter().enumerate() {
+        if token.kind != TokenKind::Punct('{') {
+            continue;
+        }
+        parser.pos = start;
+        if let Value::Object(obj) = parser.parse_value()
+            && obj.get("code").is_some()
+            && let Some(Value::Str(event, span)) = obj.get("type")
+        {
+            found.push(CommandType {
+                event: event.clone(),
+                span: *span,
+            });
+        }
+    }
+    found
+}
+
+/// The options passed to `new ForgeClient({...})`.
+#[derive(Debug, Clone, Default)]
+pub struct ClientSetup {
+    /// Enabled intents, with any `GatewayIntentBits.`-style prefix removed.
+    pub intents: Vec<String>,
+    pub events: Vec<String>,
+}
+
+/// Reads the intents and events of the first `new ForgeClient({...})` in a file.
+pub fn extract_client_setup(source: &str) -> Option<ClientSetup> {
+    let tokens = Tokenizer::new(source).tokenize();
+    let start = (0..tokens.len()).find(|&i| {
+        matches!(&tokens[i].kind, TokenKind::Ident(w) if w == "new")
+            && matches!(tokens.get(i + 1).map(|t| &t.kind), Some(TokenKind::Ident(w)) if w == "ForgeClient")
+            && tokens.get(i + 2).is_some_and(|t| t.kind == TokenKind::Punct('('))
+            && tokens.get(i + 3).is_some_and(|t| t.kind == TokenKind::Punct('{'))
+    })?;
+    let mut parser = Parser {
+        tokens: &tokens,
+        pos: start + 3,
+    };
+    let Value::Object(options) = parser.parse_value() else {
+        return None;
+    };
+
+    let names = |key: &str| -> Vec<String> {
+        match options.get(key) {
+            Some(Value::Array(items)) => items
+                .iter()
+                .filter_map(|item| match item {
+                    Value::Str(s, _) => Some(s.clone()),
+                    Value::Path(p, _) => p.rsplit('.').next().map(str::to_string),
+                    _ => None,
+                })
+                .collect(),
+            _ => Vec::new(),
+        }
+    };
+    Some(ClientSetup {
+        intents: names("intents"),
+        events: names("events"),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn extract(source: &str) -> Vec<CustomFunction> {
+        extract_custom_functions(source, "functions.js")
+            .into_iter()
+            .map(|f| f.function)
+            .collect()
+    }
+
+    #[test]
+    fn skips_definitions_in_comments() {
+        let functions = extract(
+            r#"
+// new NativeFunction({ name: "$commented" })
+/* new NativeFunction({ name: "$blocked" }) */
+new NativeFunction({ name: "$real", /* name: "$inner" */ brackets: false })
+"#,
+        );
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0].name, "$real");
+        assert_eq!(functions[0].brackets, Some(false));
+    }
+
+    #[test]
+    fn skips_definitions_in_template_literals() {
+        let functions = extract(
+            r#"
+const code = `new NativeFunction({ name: "$fake" }) ${ "}" }`;
+new NativeFunction({ name: "$real", description: `Says hi` })
+"#,
+        );
+        assert_eq!(functions.len(), 1);
+        assert_eq!(functions[0].name, "$real");
+        assert_eq!(functions[0].description.as_deref(), Some("Says hi"));
+    }
+
+    #[test]
+    fn reads_arg_type_paths_and_enums() {
+        let functions = extract(
+            r#"
+new NativeFunction({
+    name: "$channelKind",
+    args: [
+        { name: "amount", type: ArgType.Number, required: true },
+        { name: "kind", type: ArgType.Enum, enum: ChannelType },
+        { name: "mode", type: "String", enum: ["fast", "slow"], rest: true },
+    ],
+})
+"#,
+        );
+        assert_eq!(
+            functions[0].params,
+            Some(json!([
+                { "name": "amount", "required": true, "type": "Number" },
+                { "name": "kind", "type": "Enum", "enum_name": "ChannelType" },
+                { "name": "mode", "rest": true, "type": "String", "arg_enum": ["fast", "slow"] },
+            ]))
+        );
+    }
+
+    #[test]
+    fn reads_multi_line_objects() {
+        let extracted = extract_custom_functions(
+            r#"
+module.exports = {
+    name: "$greet",
+    description:
+        "Greets someone",
+    aliases: [
+        "$hello",
+        "$hi",
+    ],
+    params: [
+        "user",
+    ],
+    output: ArgType.String,
+    execute(ctx) {
+        return this.success({ name: "$nested" });
+    },
+};
+"#,
+            "greet.js",
+        );
+        assert_eq!(extracted.len(), 1);
+        let function = &extracted[0].function;
+        assert_eq!(function.name, "$greet");
+        assert_eq!(function.description.as_deref(), Some("Greets someone"));
+        assert_eq!(
+            function.alias,
+            Some(vec!["$hello".to_string(), "$hi".to_string()])
+        );
+        assert_eq!(function.params, Some(json!(["user"])));
+        assert_eq!(function.output, Some(vec!["String".to_string()]));
+        assert_eq!(function.path.as_deref(), Some("greet.js"));
+        assert!(extracted[0].params[0].required);
+    }
+
+    #[test]
+    fn reads_several_functions_per_file() {
+        let functions = extract(
+            r#"
+export default [
+    { name: "$first" },
+    { name: "$second" },
+];
+new ForgeFunction({ name: "$third" });
+"#,
+        );
+        let names: Vec<_> = functions.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["$first", "$second", "$third"]);
+    }
+
+    #[test]
+    fn reports_name_position() {
+        let source = "// header\nnew NativeFunction({\n\tname: \"$located\",\n})\n";
+        let extracted = extract_custom_functions(source, "located.js");
+        let function = &extracted[0].function;
+        assert_eq!((function.line, function.column), (Some(2), Some(8)));
+        let (start, end) = extracted[0].name_span.unwrap();
+        assert_eq!(&source[start..end], "$located");
+    }
+}
@@ -2,7 +2,7 @@ use tower_lsp::jsonrpc::Result;
 use tower_lsp::lsp_types::*;
 
 use crate::server::ForgeScriptServer;
-use crate::utils::{position_to_offset, skip_modifiers};
+use crate::utils::{offset_to_position, position_to_offset, skip_modifiers};
 
 pub async fn handle_definition(
     server: &ForgeScriptServer,
@@ -23,6 +23,25 @@ pub async fn handle_definition(
         tower_lsp::jsonrpc::Error::invalid_params("Invalid position"),
     )?;
 
+    let variables = server
+        .parsed_cache
+        .read()
+        .expect("Server: lock poisoned")
+        .get(&uri)
+        .map(|parsed| crate::variables::VariableIndex::build(&text, &parsed.functions));
+    if let Some(index) = &variables
+        && let Some(var) = index.reference_at(offset)
+        && let Some(def) = index.definition_of(var)
+    {
+        return Ok(Some(GotoDefinitionResponse::Scalar(Location {
+            uri: uri.clone(),
+            range: Range::new(
+                offset_to_position(&text, def.span.0),
+                offset_to_position(&text, def.span.1),
+            ),
+        })));
+    }
+
     let is_ident_char = |c: char| {
         c.is_alphanumeric()
             || c == '_'
@@ -74,18 +93,15 @@ pub async fn handle_definition(
         }
     }
 
-    if let Some(func) = server
-        .manager
-        .read()
-        .expect("Server: lock poisoned")
-        .get(&token)
+    if let Some(func) = server.manager_for(&uri).get(&token)
         && let (Some(path), Some(line)) = (&func.local_path, func.line)
     {
         let target_uri =
             Url::from_file_path(path).map_err(|_| tower_lsp::jsonrpc::Error::internal_error())?;
+        let position = Position::new(line, func.column.unwrap_or(0));
         return Ok(Some(GotoDefinitionResponse::Scalar(Location {
             uri: target_uri,
-            range: Range::new(Position::new(line, 0), Position::new(line, 0)),
+            range: Range::new(position, position),
         })));
     }
     Ok(None)
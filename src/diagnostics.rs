@@ -1,6 +1,6 @@
 //! Logic for transforming and publishing document diagnostics.
 
-use crate::parser::Diagnostic as ParseDiagnostic;
+use crate::parser::{Diagnostic as ParseDiagnostic, Severity};
 use crate::server::ForgeScriptServer;
 use crate::utils::offset_to_position;
 #[allow(clippy::wildcard_imports)]
@@ -36,7 +36,11 @@ pub async fn publish_diagnostics(
                     start: start_pos,
                     end: end_pos,
                 },
-                severity: Some(DiagnosticSeverity::ERROR),
+                severity: Some(match d.severity {
+                    Severity::Error => DiagnosticSeverity::ERROR,
+                    Severity::Warning => DiagnosticSeverity::WARNING,
+                }),
+                code: d.code.clone().map(NumberOrString::String),
                 message: d.message.clone(),
                 ..Default::default()
             }
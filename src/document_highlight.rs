@@ -0,0 +1,106 @@
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types::*;
+
+use crate::parser::{ParseResult, TokenKind, code_blocks};
+use crate::server::ForgeScriptServer;
+use crate::utils::{find_matching_bracket, offset_to_position, position_to_offset};
+
+/// Highlights the brackets and separators of the call under the cursor, and every
+/// other call of the same function in its code block.
+pub async fn handle_document_highlight(
+    server: &ForgeScriptServer,
+    params: DocumentHighlightParams,
+) -> Result<Option<Vec<DocumentHighlight>>> {
+    let uri = params.text_document_position_params.text_document.uri;
+    let position = params.text_document_position_params.position;
+    let Some(text) = server
+        .documents
+        .read()
+        .expect("Server: lock poisoned")
+        .get(&uri)
+        .cloned()
+    else {
+        return Ok(None);
+    };
+    let Some(parsed) = server
+        .parsed_cache
+        .read()
+        .expect("Server: lock poisoned")
+        .get(&uri)
+        .cloned()
+    else {
+        return Ok(None);
+    };
+    let Some(offset) = position_to_offset(&text, position) else {
+        return Ok(None);
+    };
+
+    let spans = highlight_spans(&text, &parsed, offset);
+    if spans.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(
+        spans
+            .into_iter()
+            .map(|(start, end)| DocumentHighlight {
+                range: Range::new(
+                    offset_to_position(&text, start),
+                    offset_to_position(&text, end),
+                ),
+                kind: Some(DocumentHighlightKind::TEXT),
+            })
+            .collect(),
+    ))
+}
+
+fn highlight_spans(text: &str, parsed: &ParseResult, offset: usize) -> Vec<(usize, usize)> {
+    let is_call = |kind: &TokenKind| matches!(kind, TokenKind::FunctionName | TokenKind::Unknown);
+    let Some(token) = parsed
+        .tokens
+        .iter()
+        .find(|t| is_call(&t.kind) && t.start <= offset && offset <= t.end)
+    else {
+        return Vec::new();
+    };
+    // Functions are sorted by start, outer calls first, like the tokens.
+    let function_at = |start: usize| {
+        let index = parsed.functions.partition_point(|f| f.span.0 < start);
+        parsed.functions.get(index).filter(|f| f.span.0 == start)
+    };
+    let func = function_at(token.start);
+
+    let mut spans = vec![(token.start, token.end)];
+    if text.as_bytes().get(token.end) == Some(&b'[')
+        && let Some(close) = find_matching_bracket(text, token.end)
+    {
+        spans.push((token.end, token.end + 1));
+        spans.push((close, close + 1));
+    }
+    let Some(func) = func else {
+        return spans;
+    };
+    spans.extend(
+        func.separator_offsets(text)
+            .into_iter()
+            .map(|sep| (sep, sep + 1)),
+    );
+
+    // Other calls resolve to the same metadata, whichever alias they use.
+    let (block_start, block_end) = code_blocks(text)
+        .into_iter()
+        .find(|&(start, end)| start <= token.start && token.start < end)
+        .unwrap_or((0, text.len()));
+    let first = parsed.tokens.partition_point(|t| t.start < block_start);
+    let last = parsed.tokens.partition_point(|t| t.start < block_end);
+    for other in &parsed.tokens[first..last] {
+        if !is_call(&other.kind) || other.start == token.start || other.end > block_end {
+            continue;
+        }
+        let same = function_at(other.start)
+            .is_some_and(|f| f.meta.name.eq_ignore_ascii_case(&func.meta.name));
+        if same {
+            spans.push((other.start, other.end));
+        }
+    }
+    spans
+}
@@ -0,0 +1,433 @@
+//! Lexing and checking of embedded JavaScript in `${ ... }` expressions.
+//!
+//! The lexer knows just enough JavaScript to find where an expression ends
+//! (strings, template literals, comments and regular expressions may contain
+//! braces), to color it and to report unbalanced brackets or unterminated
+//! literals. Deeper analysis is left to the client's TypeScript server, which
+//! receives the expressions as virtual documents.
+
+use crate::parser::{Diagnostic, Severity};
+
+/// JavaScript reserved words and literals colored as keywords.
+const KEYWORDS: &[&str] = &[
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "in",
+    "instanceof",
+    "let",
+    "new",
+    "null",
+    "of",
+    "return",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "undefined",
+    "var",
+    "void",
+    "while",
+    "yield",
+];
+
+/// Classes of JavaScript tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsTokenKind {
+    Keyword,
+    Identifier,
+    Number,
+    String,
+    Regex,
+    Comment,
+    Operator,
+    Punctuation,
+}
+
+/// A lexed JavaScript token with absolute byte offsets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsToken {
+    pub kind: JsTokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Tokens and problems of one embedded expression.
+#[derive(Debug, Clone, Default)]
+pub struct JsAnalysis {
+    pub tokens: Vec<JsToken>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+struct Lexer<'a> {
+    src: &'a str,
+    pos: usize,
+    /// Whether a `/` at the current position would start a regular expression.
+    regex_allowed: bool,
+    /// Open brackets with their offsets; template substitutions push `{` too.
+    stack: Vec<(char, usize)>,
+    out: JsAnalysis,
+}
+
+impl<'a> Lexer<'a> {
+    fn new(src: &'a str, pos: usize) -> Self {
+        Self {
+            src,
+            pos,
+            regex_allowed: true,
+            stack: Vec::new(),
+            out: JsAnalysis::default(),
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn peek_at(&self, n: usize) -> Option<char> {
+        self.src[self.pos..].chars().nth(n)
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+
+    fn push(&mut self, kind: JsTokenKind, start: usize) {
+        self.out.tokens.push(JsToken {
+            kind,
+            start,
+            end: self.pos,
+        });
+        self.regex_allowed = match kind {
+            JsTokenKind::Identifier | JsTokenKind::Number | JsTokenKind::String => false,
+            JsTokenKind::Regex => false,
+            JsTokenKind::Keyword => !matches!(
+                &self.src[start..self.pos],
+                "this" | "true" | "false" | "null" | "undefined"
+            ),
+            JsTokenKind::Punctuation => !matches!(&self.src[start..self.pos], ")" | "]" | "}"),
+            JsTokenKind::Operator => true,
+            JsTokenKind::Comment => self.regex_allowed,
+        };
+    }
+
+    fn error(&mut self, message: impl Into<String>, start: usize, end: usize) {
+        self.out.diagnostics.push(Diagnostic {
+            message: message.into(),
+            start,
+            end,
+            severity: Severity::Error,
+            code: None,
+        });
+    }
+
+    /// Lexes until the `}` that closes the expression, returning its offset.
+    fn run(&mut self) -> Option<usize> {
+        while let Some(c) = self.peek() {
+            let start = self.pos;
+            match c {
+                c if c.is_whitespace() => {
+                    self.bump();
+                }
+                '/' if self.peek_at(1) == Some('/') => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                    self.push(JsTokenKind::Comment, start);
+                }
+                '/' if self.peek_at(1) == Some('*') => {
+                    self.pos += 2;
+                    match self.src[self.pos..].find("*/") {
+                        Some(end) => self.pos += end + 2,
+                        None => {
+                            self.pos = self.src.len();
+                            self.error("Unterminated comment", start, self.pos);
+                        }
+                    }
+                    self.push(JsTokenKind::Comment, start);
+                }
+                '/' if self.regex_allowed => self.scan_regex(),
+                '"' | '\'' => self.scan_string(c),
+                '`' => {
+                    self.bump();
+                    self.scan_template(start);
+                }
+                c if c.is_ascii_digit()
+                    || (c == '.' && self.peek_at(1).is_some_and(|d| d.is_ascii_digit())) =>
+                {
+                    while self
+                        .peek()
+                        .is_some_and(|c| c.is_ascii_alphanumeric() || c == '.' || c == '_')
+                    {
+                        self.bump();
+                    }
+                    self.push(JsTokenKind::Number, start);
+                }
+                c if c.is_alphabetic() || c == '_' || c == '$' => {
+                    while self
+                        .peek()
+                        .is_some_and(|c| c.is_alphanumeric() || c == '_' || c == '$')
+                    {
+                        self.bump();
+                    }
+                    let kind = if KEYWORDS.contains(&&self.src[start..self.pos]) {
+                        JsTokenKind::Keyword
+                    } else {
+                        JsTokenKind::Identifier
+                    };
+                    self.push(kind, start);
+                }
+                '(' | '[' | '{' => {
+                    self.bump();
+                    self.stack.push((c, start));
+                    self.push(JsTokenKind::Punctuation, start);
+                }
+                ')' | ']' | '}' => {
+                    if c == '}' && self.stack.last().is_some_and(|&(open, _)| open != '{') {
+                        // `(` and `[` cannot span a `}`: they were left open before it.
+                        let Some(brace) = self.stack.iter().rposition(|&(open, _)| open == '{')
+                        else {
+                            return Some(start);
+                        };
+                        for (open, at) in self.stack.split_off(brace + 1) {
+                            self.unclosed(open, at);
+                        }
+                    }
+                    match self.stack.pop() {
+                        None if c == '}' => return Some(start),
+                        None => {
+                            self.bump();
+                            self.error(format!("Unexpected `{c}`"), start, self.pos);
+                        }
+                        Some((open, _)) if open == '{' && c == '}' && self.in_template() => {
+                            // End of a `${...}` substitution: resume the template literal.
+                            self.bump();
+                            self.push(JsTokenKind::Punctuation, start);
+                            self.stack.pop();
+                            let template_start = self.pos;
+                            self.scan_template(template_start);
+                            continue;
+                        }
+                        Some((open, _)) => {
+                            self.bump();
+                            let expected = closing(open);
+                            if expected != c {
+                                self.error(
+                                    format!(
+                                        "Expected `{expected}` to close `{open}` but found `{c}`"
+                                    ),
+                                    start,
+                                    self.pos,
+                                );
+                            }
+                        }
+                    }
+                    self.push(JsTokenKind::Punctuation, start);
+                }
+                ',' | ';' | '.' => {
+                    self.bump();
+                    self.push(JsTokenKind::Punctuation, start);
+                }
+                _ => {
+                    self.bump();
+                    while self.peek().is_some_and(|c| "+-*%=<>!&|^~?:".contains(c)) {
+                        self.bump();
+                    }
+                    self.push(JsTokenKind::Operator, start);
+                }
+            }
+        }
+        None
+    }
+
+    fn unclosed(&mut self, open: char, at: usize) {
+        self.error(
+            format!("Unclosed `{open}` in JavaScript expression"),
+            at,
+            at + 1,
+        );
+    }
+
+    /// Whether the innermost open bracket is a template substitution marker.
+    fn in_template(&self) -> bool {
+        self.stack.last().is_some_and(|&(c, _)| c == '`')
+    }
+
+    fn scan_string(&mut self, quote: char) {
+        let start = self.pos;
+        self.bump();
+        loop {
+            match self.bump() {
+                Some('\\') => {
+                    self.bump();
+                }
+                Some(c) if c == quote => break,
+                Some('\n') | None => {
+                    // Give back the `}` that most likely closes the expression.
+                    if let Some(brace) = self.src[start..self.pos].find('}') {
+                        self.pos = start + brace;
+                    }
+                    self.error("Unterminated string literal", start, self.pos);
+                    break;
+                }
+                Some(_) => {}
+            }
+        }
+        self.push(JsTokenKind::String, start);
+    }
+
+    /// Scans template text up to the closing backtick or the next `${`.
+    fn scan_template(&mut self, start: usize) {
+        loop {
+            match self.peek() {
+                Some('\\') => {
+                    self.bump();
+                    self.bump();
+                }
+                Some('`') => {
+                    self.bump();
+                    self.push(JsTokenKind::String, start);
+                    return;
+                }
+                Some('$') if self.peek_at(1) == Some('{') => {
+                    if self.pos > start {
+                        self.push(JsTokenKind::String, start);
+                    }
+                    let open = self.pos;
+                    self.pos += 2;
+                    self.push(JsTokenKind::Punctuation, open);
+                    self.stack.push(('`', open));
+                    self.stack.push(('{', open));
+                    return;
+                }
+                Some(_) => {
+                    self.bump();
+                }
+                None => {
+                    self.error("Unterminated template literal", start, self.pos);
+                    self.push(JsTokenKind::String, start);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn scan_regex(&mut self) {
+        let start = self.pos;
+        self.bump();
+        let mut in_class = false;
+        loop {
+            match self.bump() {
+                Some('\\') => {
+                    self.bump();
+                }
+                Some('[') => in_class = true,
+                Some(']') => in_class = false,
+                Some('/') if !in_class => break,
+                Some('\n') | None => {
+                    self.error("Unterminated regular expression", start, self.pos);
+                    break;
+                }
+                Some(_) => {}
+            }
+        }
+        while self.peek().is_some_and(|c| c.is_ascii_alphabetic()) {
+            self.bump();
+        }
+        self.push(JsTokenKind::Regex, start);
+    }
+}
+
+fn closing(open: char) -> char {
+    match open {
+        '(' => ')',
+        '[' => ']',
+        _ => '}',
+    }
+}
+
+/// Lexes and checks the expression whose `{` is at `open`, returning the offset of the `}`
+/// closing it (braces in literals are skipped) along with the analysis.
+pub fn analyze_expression(code: &str, open: usize) -> Option<(usize, JsAnalysis)> {
+    let mut lexer = Lexer::new(code, open + 1);
+    let close = lexer.run()?;
+    let unclosed: Vec<_> = lexer.stack.drain(..).filter(|&(c, _)| c != '`').collect();
+    for (c, at) in unclosed {
+        lexer.unclosed(c, at);
+    }
+    if code[open + 1..close].trim().is_empty() {
+        lexer.error("Empty JavaScript expression", open - 1, close + 1);
+    }
+    Some((close, lexer.out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn messages(analysis: &JsAnalysis) -> Vec<(&str, usize)> {
+        analysis
+            .diagnostics
+            .iter()
+            .map(|d| (d.message.as_str(), d.start))
+            .collect()
+    }
+
+    #[test]
+    fn brace_ends_expression_with_open_paren() {
+        let (close, analysis) = analyze_expression("${ (a }", 1).unwrap();
+        assert_eq!(close, 6);
+        assert_eq!(
+            messages(&analysis),
+            [("Unclosed `(` in JavaScript expression", 3)]
+        );
+    }
+
+    #[test]
+    fn brace_unwinds_to_nearest_open_brace() {
+        let (close, analysis) = analyze_expression("${ {(a[} }", 1).unwrap();
+        assert_eq!(close, 9);
+        assert_eq!(
+            messages(&analysis),
+            [
+                ("Unclosed `(` in JavaScript expression", 4),
+                ("Unclosed `[` in JavaScript expression", 6),
+            ]
+        );
+    }
+
+    #[test]
+    fn brace_unwinds_inside_template_substitution() {
+        let (close, _) = analyze_expression("${ `x${ (a }` }", 1).unwrap();
+        assert_eq!(close, 14);
+    }
+
+    #[test]
+    fn unterminated_string_stops_at_closing_brace() {
+        let (close, analysis) = analyze_expression("${ 'abc } $log[x]", 1).unwrap();
+        assert_eq!(close, 8);
+        assert_eq!(messages(&analysis), [("Unterminated string literal", 3)]);
+        assert_eq!(
+            analysis.tokens.last().map(|t| (t.start, t.end)),
+            Some((3, 8))
+        );
+    }
+}
@@ -0,0 +1,83 @@
+//! Validation of command event types against the loaded `events.json` metadata.
+//!
+//! Every command object's `type:` must name a known event, and the intents that
+//! event needs (and the event itself) should be enabled in the client setup
+//! file from the config.
+
+use std::path::Path;
+
+use crate::config_schema::suggest_key;
+use crate::custom_js::{ClientSetup, extract_command_types};
+use crate::parser::{Diagnostic, Severity};
+use crate::utils::Event;
+
+/// Checks the `type:` of every command object in a JS/TS file.
+///
+/// Nothing is reported until event metadata has been loaded.
+pub fn check_command_events(
+    source: &str,
+    events: &[Event],
+    client: Option<(&Path, &ClientSetup)>,
+) -> Vec<Diagnostic> {
+    if events.is_empty() {
+        return Vec::new();
+    }
+    let names: Vec<&str> = events.iter().map(|e| e.name.as_str()).collect();
+
+    let mut diagnostics = Vec::new();
+    for command in extract_command_types(source) {
+        let (start, end) = command.span;
+        let Some(event) = events.iter().find(|e| e.name == command.event) else {
+            let hint = suggest_key(&command.event, &names)
+                .map(|name| format!(" — did you mean `{name}`?"))
+                .unwrap_or_default();
+            diagnostics.push(Diagnostic {
+                message: format!("Unknown event type `{}`{hint}", command.event),
+                start,
+                end,
+                severity: Severity::Warning,
+                code: None,
+            });
+            continue;
+        };
+
+        let Some((client_path, setup)) = client else {
+            continue;
+        };
+        if !setup.events.is_empty() && !setup.events.contains(&event.name) {
+            diagnostics.push(Diagnostic {
+                message: format!(
+                    "Event `{}` is not listed in the client `events` in {}",
+                    event.name,
+                    client_path.display()
+                ),
+                start,
+                end,
+                severity: Severity::Warning,
+                code: None,
+            });
+        }
+        let missing: Vec<String> = event
+            .intents
+            .iter()
+            .flatten()
+            .filter(|intent| !setup.intents.contains(intent))
+            .map(|intent| format!("`{intent}`"))
+            .collect();
+        if !missing.is_empty() {
+            diagnostics.push(Diagnostic {
+                message: format!(
+                    "Event `{}` needs the intents {}, which are not enabled in {}",
+                    event.name,
+                    missing.join(", "),
+                    client_path.display()
+                ),
+                start,
+                end,
+                severity: Severity::Warning,
+                code: None,
+            });
+        }
+    }
+    diagnostics
+}
@@ -23,20 +23,65 @@ pub async fn handle_folding_range(
         .cloned()
         .ok_or(tower_lsp::jsonrpc::Error::invalid_params("No text"))?;
 
+    let line_folding_only = *server
+        .line_folding_only
+        .read()
+        .expect("Server: lock poisoned");
+
     let mut ranges = Vec::new();
-    for func in &parsed.functions {
-        let start = crate::utils::offset_to_position(&text, func.span.0);
-        let end = crate::utils::offset_to_position(&text, func.span.1);
-        if start.line < end.line {
+    // `closed` ranges end in a delimiter that stays visible when only whole lines fold.
+    let mut push = |(start, end): (usize, usize), kind, label: String, closed: bool| {
+        let start = crate::utils::offset_to_position(&text, start);
+        let end = crate::utils::offset_to_position(&text, end);
+        let end_line = if line_folding_only && closed {
+            end.line.saturating_sub(1)
+        } else {
+            end.line
+        };
+        if start.line < end_line {
             ranges.push(FoldingRange {
                 start_line: start.line,
-                start_character: Some(start.character),
-                end_line: end.line,
-                end_character: Some(end.character),
-                kind: Some(FoldingRangeKind::Region),
-                collapsed_text: Some(func.name.clone()),
+                start_character: (!line_folding_only).then_some(start.character),
+                end_line,
+                end_character: (!line_folding_only).then_some(end.character),
+                kind: Some(kind),
+                collapsed_text: Some(label),
             });
         }
+    };
+
+    for (start, end) in crate::parser::code_blocks(&text) {
+        push(
+            (start, end),
+            FoldingRangeKind::Region,
+            "…".to_string(),
+            true,
+        );
+    }
+    for func in &parsed.functions {
+        if func.name.eq_ignore_ascii_case("c") {
+            push(
+                func.span,
+                FoldingRangeKind::Comment,
+                "$c[…]".to_string(),
+                true,
+            );
+            continue;
+        }
+        let label = match func.args.as_ref().map(Vec::len) {
+            Some(1) => format!("${}[…] (1 arg)", func.name),
+            Some(count) => format!("${}[…] ({count} args)", func.name),
+            None => format!("${}", func.name),
+        };
+        push(func.span, FoldingRangeKind::Region, label, true);
+        // An argument starting on the call's line would fold the same lines as the call.
+        let call_line = crate::utils::offset_to_position(&text, func.span.0).line;
+        for (_, span) in func.args.iter().flatten() {
+            if crate::utils::offset_to_position(&text, span.0).line == call_line {
+                continue;
+            }
+            push(*span, FoldingRangeKind::Region, "…".to_string(), false);
+        }
     }
     Ok(Some(ranges))
 }
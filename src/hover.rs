@@ -136,8 +136,7 @@ pub async fn handle_hover(
     }
 
     // Lookup metadata for the identified function.
-    let mgr = server.manager.read().expect("Hover: manager lock poisoned");
-    let mgr_inner = mgr.clone();
+    let mgr_inner = server.manager_for(&uri);
 
     if let Some(func_ref) = mgr_inner.get(&clean_token) {
         let func_name = &func_ref.name;
@@ -198,6 +197,10 @@ pub async fn handle_hover(
         }
         md.push_str("```\n");
 
+        if let Some(since) = crate::version::since_label(&func_ref) {
+            md.push_str(&format!("*{since}*\n\n"));
+        }
+
         if !func_description.is_empty() {
             md.push_str(func_description);
             md.push('\n');
@@ -4,29 +4,49 @@
 //! On WASM, this provides `#[wasm_bindgen]` exports for browser integration.
 
 // Modules shared between native and WASM targets:
+pub mod condition;
+pub mod config_schema;
+pub mod control_flow;
+pub mod embedded_js;
 pub mod metadata;
 pub mod parser;
 pub mod utils;
+pub mod variables;
+pub mod version;
 
 // Modules used only by the native LSP server:
 #[cfg(not(target_arch = "wasm32"))]
+pub mod color;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod commands;
 pub mod completion;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod custom_js;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod definition;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod depth;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod diagnostics;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod document_highlight;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod events;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod folding_range;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod hover;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod progress;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod selection_range;
 pub mod semantic;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod server;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod signature_help;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod workspace;
 
 // WASM-specific API module:
 #[cfg(target_arch = "wasm32")]
@@ -1,19 +1,35 @@
 //! Entry point for the ForgeLSP server (native only).
 //!
-//! This module initializes the MetadataManager, loads configuration from forgeconfig.json,
-//! and starts the Tower LSP server on stdin/stdout.
+//! This module loads configuration from forgeconfig.json, initializes the working directory's
+//! metadata from cache, and starts the Tower LSP server on stdin/stdout.
 
+#[cfg(not(target_arch = "wasm32"))]
+mod color;
 #[cfg(not(target_arch = "wasm32"))]
 mod commands;
 #[cfg(not(target_arch = "wasm32"))]
 mod completion;
 #[cfg(not(target_arch = "wasm32"))]
+mod condition;
+#[cfg(not(target_arch = "wasm32"))]
+mod config_schema;
+#[cfg(not(target_arch = "wasm32"))]
+mod control_flow;
+#[cfg(not(target_arch = "wasm32"))]
+mod custom_js;
+#[cfg(not(target_arch = "wasm32"))]
 mod definition;
 #[cfg(not(target_arch = "wasm32"))]
 mod depth;
 #[cfg(not(target_arch = "wasm32"))]
 mod diagnostics;
 #[cfg(not(target_arch = "wasm32"))]
+mod document_highlight;
+#[cfg(not(target_arch = "wasm32"))]
+mod embedded_js;
+#[cfg(not(target_arch = "wasm32"))]
+mod events;
+#[cfg(not(target_arch = "wasm32"))]
 mod folding_range;
 #[cfg(not(target_arch = "wasm32"))]
 mod hover;
@@ -22,6 +38,10 @@ mod metadata;
 #[cfg(not(target_arch = "wasm32"))]
 mod parser;
 #[cfg(not(target_arch = "wasm32"))]
+mod progress;
+#[cfg(not(target_arch = "wasm32"))]
+mod selection_range;
+#[cfg(not(target_arch = "wasm32"))]
 mod semantic;
 #[cfg(not(target_arch = "wasm32"))]
 mod server;
@@ -29,6 +49,12 @@ mod server;
 mod signature_help;
 #[cfg(not(target_arch = "wasm32"))]
 mod utils;
+#[cfg(not(target_arch = "wasm32"))]
+mod variables;
+#[cfg(not(target_arch = "wasm32"))]
+mod version;
+#[cfg(not(target_arch = "wasm32"))]
+mod workspace;
 
 #[cfg(not(target_arch = "wasm32"))]
 use std::collections::HashMap;
@@ -40,12 +66,12 @@ use tokio::io::{stdin, stdout};
 #[cfg(not(target_arch = "wasm32"))]
 use tower_lsp::{LspService, Server};
 
-#[cfg(not(target_arch = "wasm32"))]
-use crate::metadata::MetadataManager;
 #[cfg(not(target_arch = "wasm32"))]
 use crate::server::ForgeScriptServer;
 #[cfg(not(target_arch = "wasm32"))]
-use crate::utils::{load_forge_config, load_forge_config_full};
+use crate::utils::load_forge_config_full;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::workspace::WorkspaceFolderState;
 
 /// Configures and starts the ForgeScript Language Server.
 #[cfg(not(target_arch = "wasm32"))]
@@ -55,10 +81,10 @@ async fn main() -> anyhow::Result<()> {
     let workspace_folders = vec![std::env::current_dir()?];
 
     // Initialize the specialized logger for ForgeLSP.
-    let full_config = load_forge_config_full(&workspace_folders).map(|(c, _)| c);
-    let log_level = full_config
+    let fallback = load_forge_config_full(&workspace_folders);
+    let log_level = fallback
         .as_ref()
-        .and_then(|c| c.log_level)
+        .and_then(|(c, _)| c.log_level)
         .unwrap_or(crate::utils::LogLevel::Info);
 
     crate::utils::init_logger(workspace_folders[0].clone(), log_level)?;
@@ -67,75 +93,40 @@ async fn main() -> anyhow::Result<()> {
         &format!("ForgeLSP starting up (Level: {:?})", log_level),
     );
 
-    // Resolve metadata URLs from forgeconfig.json or use the default production URL.
-    let fetch_urls = load_forge_config(&workspace_folders).unwrap_or_else(|| {
-        vec!["https://raw.githubusercontent.com/tryforge/forgescript/dev/metadata/functions.json"]
-            .into_iter()
-            .map(String::from)
-            .collect()
-    });
-
-    // Initialize the MetadataManager with a local cache directory and remote URLs.
+    // Serve the working directory from the disk cache right away; remote sources are
+    // refreshed in the background once the client has finished initializing.
     crate::utils::forge_log(
         crate::utils::LogLevel::Debug,
         "Initializing MetadataManager...",
     );
-    let manager = Arc::new(MetadataManager::new("./.cache", fetch_urls, None)?);
-
-    // Perform an initial fetch of all metadata sources.
-    crate::utils::forge_log(crate::utils::LogLevel::Info, "Fetching metadata sources...");
-    manager.load_all().await?;
+    let root_state =
+        WorkspaceFolderState::load(workspace_folders[0].clone(), fallback.as_ref(), &[], None)?;
     crate::utils::forge_log(
         crate::utils::LogLevel::Info,
         &format!(
-            "Successfully indexed {} functions",
-            manager.function_count()
+            "Indexed {} functions from cache",
+            root_state.manager.function_count()
         ),
     );
 
-    // Load any project-specific custom function definitions from the configuration.
-    if let Some((config, _)) = load_forge_config_full(&workspace_folders)
-        && let Some(custom_funcs) = config.custom_functions
-        && !custom_funcs.is_empty()
-    {
-        manager
-            .add_custom_functions(custom_funcs)
-            .expect("Failed to add custom functions");
-        crate::utils::forge_log(
-            crate::utils::LogLevel::Info,
-            "Loaded project-specific custom functions",
-        );
-    }
-
-    // Wrap state in RwLocks for shared mutable access during LSP requests.
-    let manager_wrapped = Arc::new(RwLock::new(manager));
-    let config_wrapped = Arc::new(RwLock::new(full_config.clone()));
-
     // Instantiate the LSP service with the ForgeScriptServer state.
-    let (service, socket) = LspService::new(|client| {
-        let colors = full_config
-            .as_ref()
-            .and_then(|c| c.function_colors.clone())
-            .unwrap_or_default();
-
-        let consistent = full_config
-            .as_ref()
-            .and_then(|c| c.consistent_function_colors)
-            .unwrap_or(false);
-
-        ForgeScriptServer {
-            client,
-            manager: manager_wrapped.clone(),
-            documents: Arc::new(RwLock::new(HashMap::new())),
-            parsed_cache: Arc::new(RwLock::new(HashMap::new())),
-            workspace_folders: Arc::new(RwLock::new(workspace_folders.clone())),
-            multiple_function_colors: Arc::new(RwLock::new(true)),
-            consistent_function_colors: Arc::new(RwLock::new(consistent)),
-            function_colors: Arc::new(RwLock::new(colors)),
-            config: config_wrapped,
-            cursor_positions: Arc::new(RwLock::new(HashMap::new())),
-        }
-    });
+    let (service, socket) = LspService::build(|client| ForgeScriptServer {
+        client,
+        documents: Arc::new(RwLock::new(HashMap::new())),
+        parsed_cache: Arc::new(RwLock::new(HashMap::new())),
+        semantic_tokens: Arc::new(RwLock::new(HashMap::new())),
+        workspace_folders: Arc::new(RwLock::new(workspace_folders.clone())),
+        cursor_positions: Arc::new(RwLock::new(HashMap::new())),
+        supports_work_done_progress: Arc::new(RwLock::new(false)),
+        line_folding_only: Arc::new(RwLock::new(false)),
+        supports_forge_highlights: Arc::new(RwLock::new(false)),
+        folder_states: Arc::new(RwLock::new(vec![root_state])),
+    })
+    .custom_method(
+        "forge/embeddedDocuments",
+        ForgeScriptServer::embedded_documents,
+    )
+    .finish();
 
     // Listen for incoming LSP requests over standard IO.
     crate::utils::forge_log(
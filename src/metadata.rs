@@ -10,11 +10,18 @@ use std::fs;
 use std::path::PathBuf;
 #[cfg(not(target_arch = "wasm32"))]
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use anyhow::{Result, anyhow};
 use futures::future;
 use reqwest::Client;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::StatusCode;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::header::{ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED};
 use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
@@ -23,7 +30,21 @@ use tower_lsp::Client as LspClient;
 #[cfg(not(target_arch = "wasm32"))]
 use tower_lsp::lsp_types::{MessageActionItem, MessageType};
 
-use crate::utils::Event;
+use crate::utils::{Event, FunctionPrecedence};
+
+/// Callback invoked with each metadata URL once its fetch has settled.
+pub type SourceLoaded<'a> = &'a (dyn Fn(&str) + Send + Sync);
+
+/// Callback invoked with `(url, completed, total)` while metadata sources load.
+pub type SourceProgress<'a> = &'a (dyn Fn(&str, usize, usize) + Send + Sync);
+
+/// Callback invoked with `(path, completed, total)` while custom function files load.
+#[cfg(not(target_arch = "wasm32"))]
+pub type FileProgress<'a> = &'a (dyn Fn(&Path, usize, usize) + Send + Sync);
+
+/// Function metadata source used when no `forgeconfig.json` provides one.
+pub const DEFAULT_FUNCTIONS_URL: &str =
+    "https://raw.githubusercontent.com/tryforge/forgescript/dev/metadata/functions.json";
 
 /// Comprehensive metadata for a ForgeScript command/function.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
@@ -49,6 +70,8 @@ pub struct Function {
     pub local_path: Option<PathBuf>,
     #[serde(skip)]
     pub line: Option<u32>,
+    #[serde(skip)]
+    pub column: Option<u32>,
 }
 
 impl Function {
@@ -91,6 +114,66 @@ impl Function {
     }
 }
 
+/// Describes where a function definition came from, for conflict messages.
+pub fn origin_of(func: &Function) -> String {
+    if let Some(path) = &func.local_path {
+        return match func.line {
+            Some(line) => format!("{}:{}", path.display(), line + 1),
+            None => path.display().to_string(),
+        };
+    }
+    if func.category.as_deref() == Some("custom") {
+        return "forgeconfig.json".to_string();
+    }
+    match (&func.extension, &func.source_url) {
+        (Some(ext), Some(url)) => format!("{ext} ({url})"),
+        (None, Some(url)) => url.clone(),
+        _ => "built-in metadata".to_string(),
+    }
+}
+
+/// Whether the function is one of the user's custom functions.
+pub fn is_custom(func: &Function) -> bool {
+    func.category.as_deref() == Some("custom")
+}
+
+/// Two definitions registered under the same name or alias; only `kept` is served.
+#[derive(Debug, Clone)]
+pub struct FunctionConflict {
+    pub name: String,
+    pub kept: Arc<Function>,
+    pub shadowed: Arc<Function>,
+}
+
+impl FunctionConflict {
+    /// Describes the conflict with the origins of both definitions.
+    pub fn describe(&self) -> String {
+        format!(
+            "`{}` from {} shadows the definition from {}",
+            self.name,
+            origin_of(&self.kept),
+            origin_of(&self.shadowed)
+        )
+    }
+
+    /// Explains a custom/built-in conflict from the custom definition's point of view.
+    pub fn custom_message(&self) -> String {
+        if is_custom(&self.kept) {
+            format!(
+                "Custom function `{}` overrides the built-in from {}; set `function_precedence` to \"builtin\" to keep it",
+                self.name,
+                origin_of(&self.shadowed)
+            )
+        } else {
+            format!(
+                "Custom function `{}` is ignored: the built-in from {} takes precedence",
+                self.name,
+                origin_of(&self.kept)
+            )
+        }
+    }
+}
+
 /// Description of an individual argument within a ForgeScript function.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
 #[serde(rename_all = "camelCase")]
@@ -111,6 +194,16 @@ pub struct Arg {
     pub pointer_property: Option<String>,
 }
 
+/// Validators and timestamp stored next to each cached metadata file.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct CacheMeta {
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Unix timestamp (seconds) of the last successful fetch or revalidation.
+    fetched_at: u64,
+}
+
 /// Handles network requests and local disk caching of JSON metadata.
 #[derive(Clone, Debug)]
 pub struct Fetcher {
@@ -119,12 +212,20 @@ pub struct Fetcher {
     cache_dir: PathBuf,
     #[cfg(not(target_arch = "wasm32"))]
     client: Option<LspClient>,
+    #[cfg(not(target_arch = "wasm32"))]
+    cache_ttl: Option<Duration>,
 }
 
 impl Fetcher {
     /// Initializes a Fetcher with a dedicated cache directory and optional LSP client for error reporting.
+    ///
+    /// Cached files younger than `cache_ttl` are served without touching the network.
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn new(cache_dir: impl Into<PathBuf>, client: Option<LspClient>) -> Self {
+    pub fn new(
+        cache_dir: impl Into<PathBuf>,
+        client: Option<LspClient>,
+        cache_ttl: Option<Duration>,
+    ) -> Self {
         let dir = cache_dir.into();
         if !dir.exists() {
             fs::create_dir_all(&dir).expect("Failed to create cache directory");
@@ -135,6 +236,7 @@ impl Fetcher {
                 .expect("Failed to build HTTP client"),
             cache_dir: dir,
             client,
+            cache_ttl,
         }
     }
 
@@ -199,7 +301,49 @@ impl Fetcher {
             .and_then(|data| serde_json::from_str(&data).ok())
     }
 
+    /// Reads a previously cached response for a URL without touching the network.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_cached<T: DeserializeOwned>(&self, url: &str) -> Option<T> {
+        self.get_from_cache(&self.cache_path(url))
+    }
+
+    /// Location of the validator sidecar for a cached metadata file.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn cache_meta_path(path: &Path) -> PathBuf {
+        path.with_extension("meta.json")
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_cache_meta(&self, path: &Path) -> Option<CacheMeta> {
+        self.get_from_cache(&Self::cache_meta_path(path))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn write_cache_meta(&self, path: &Path, meta: &CacheMeta) {
+        if let Ok(data) = serde_json::to_string(meta) {
+            fs::write(Self::cache_meta_path(path), data).ok();
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn now_secs() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+    }
+
+    /// Checks whether a cache entry is still within the configured TTL.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn is_fresh(&self, meta: &CacheMeta) -> bool {
+        self.cache_ttl
+            .is_some_and(|ttl| Self::now_secs().saturating_sub(meta.fetched_at) < ttl.as_secs())
+    }
+
     /// Fetches JSON from a URL and updates the local cache, falling back to cache on failure.
+    ///
+    /// Fresh cache entries are returned without a request; stale ones are revalidated
+    /// with `If-None-Match`/`If-Modified-Since` so an unchanged file costs a `304`.
     #[cfg(not(target_arch = "wasm32"))]
     pub async fn fetch_or_cache<T: DeserializeOwned + Serialize>(
         &self,
@@ -207,17 +351,69 @@ impl Fetcher {
         mandatory: bool,
     ) -> Result<T> {
         let path = self.cache_path(url);
+        let mut meta = self.read_cache_meta(&path).filter(|_| path.exists());
+
+        if let Some(m) = &meta
+            && self.is_fresh(m)
+            && let Some(cached) = self.get_from_cache::<T>(&path)
+        {
+            crate::utils::forge_log(
+                crate::utils::LogLevel::Debug,
+                &format!("Cache is fresh for {url}, skipping network"),
+            );
+            return Ok(cached);
+        }
 
         loop {
-            let res = self.http.get(url).send().await;
+            let mut request = self.http.get(url);
+            if let Some(m) = &meta {
+                if let Some(etag) = &m.etag {
+                    request = request.header(IF_NONE_MATCH, etag);
+                }
+                if let Some(modified) = &m.last_modified {
+                    request = request.header(IF_MODIFIED_SINCE, modified);
+                }
+            }
+            let res = request.send().await;
 
             match res {
+                Ok(resp) if resp.status() == StatusCode::NOT_MODIFIED => {
+                    let validators = meta.take().unwrap_or_default();
+                    if let Some(cached) = self.get_from_cache::<T>(&path) {
+                        crate::utils::forge_log(
+                            crate::utils::LogLevel::Debug,
+                            &format!("{url} not modified, reusing cache"),
+                        );
+                        self.write_cache_meta(
+                            &path,
+                            &CacheMeta {
+                                fetched_at: Self::now_secs(),
+                                ..validators
+                            },
+                        );
+                        return Ok(cached);
+                    }
+                    // The cache is unreadable, so retry without validators.
+                    continue;
+                }
                 Ok(resp) if resp.status().is_success() || mandatory => {
+                    let header = |name| {
+                        resp.headers()
+                            .get(name)
+                            .and_then(|v| v.to_str().ok())
+                            .map(String::from)
+                    };
+                    let validators = CacheMeta {
+                        etag: header(ETAG),
+                        last_modified: header(LAST_MODIFIED),
+                        fetched_at: Self::now_secs(),
+                    };
                     let body = resp.text().await?;
                     if let Ok(json) = serde_json::from_str::<JsonValue>(&body) {
                         if json.is_array() || json.is_object() {
                             if let Ok(parsed) = serde_json::from_value::<T>(json) {
                                 fs::write(&path, &body)?;
+                                self.write_cache_meta(&path, &validators);
                                 return Ok(parsed);
                             }
                         }
@@ -286,18 +482,20 @@ impl Fetcher {
     }
 
     /// Concurrent fetch of multiple function metadata sources.
+    ///
+    /// `on_loaded` is called with each URL as soon as its fetch settles.
     pub async fn fetch_all(
         &self,
         urls: &[String],
+        on_loaded: SourceLoaded<'_>,
     ) -> Result<std::collections::HashMap<String, Vec<Function>>> {
         let tasks = urls.iter().map(|u| {
             let u = u.clone();
             let this = self.clone();
             async move {
-                (
-                    u.clone(),
-                    this.fetch_or_cache::<Vec<Function>>(&u, true).await,
-                )
+                let res = this.fetch_or_cache::<Vec<Function>>(&u, true).await;
+                on_loaded(&u);
+                (u, res)
             }
         });
         let results = future::join_all(tasks).await;
@@ -331,6 +529,11 @@ impl Fetcher {
                     .insert(self.cache_path(&url.replace("functions.json", "events.json")));
             }
         }
+        let sidecars: Vec<PathBuf> = all_possible_active
+            .iter()
+            .map(|p| Self::cache_meta_path(p))
+            .collect();
+        all_possible_active.extend(sidecars);
 
         for entry in fs::read_dir(&self.cache_dir)? {
             let path = entry?.path();
@@ -342,13 +545,20 @@ impl Fetcher {
     }
 
     /// Fetches enum definitions from remote sources.
-    pub async fn fetch_all_enums(&self, urls: &[String]) -> Result<HashMap<String, Vec<String>>> {
+    pub async fn fetch_all_enums(
+        &self,
+        urls: &[String],
+        on_loaded: SourceLoaded<'_>,
+    ) -> Result<HashMap<String, Vec<String>>> {
         let tasks = urls.iter().map(|u| {
             let u = u.clone();
             let this = self.clone();
             async move {
-                this.fetch_or_cache::<HashMap<String, Vec<String>>>(&u, false)
-                    .await
+                let res = this
+                    .fetch_or_cache::<HashMap<String, Vec<String>>>(&u, false)
+                    .await;
+                on_loaded(&u);
+                res
             }
         });
         let results = future::join_all(tasks).await;
@@ -360,11 +570,19 @@ impl Fetcher {
     }
 
     /// Fetches event definitions from remote sources.
-    pub async fn fetch_all_events(&self, urls: &[String]) -> Result<Vec<Event>> {
+    pub async fn fetch_all_events(
+        &self,
+        urls: &[String],
+        on_loaded: SourceLoaded<'_>,
+    ) -> Result<Vec<Event>> {
         let tasks = urls.iter().map(|u| {
             let u = u.clone();
             let this = self.clone();
-            async move { this.fetch_or_cache::<Vec<Event>>(&u, false).await }
+            async move {
+                let res = this.fetch_or_cache::<Vec<Event>>(&u, false).await;
+                on_loaded(&u);
+                res
+            }
         });
         let results = future::join_all(tasks).await;
         let mut out = Vec::new();
@@ -497,8 +715,14 @@ pub struct MetadataManager {
     trie: Arc<RwLock<FunctionTrie>>,
     pub enums: Arc<RwLock<HashMap<String, Vec<String>>>>,
     pub events: Arc<RwLock<Vec<Event>>>,
+    /// Name and alias collisions involving metadata sources, keyed by lowercase name.
+    conflicts: Arc<RwLock<HashMap<String, FunctionConflict>>>,
+    precedence: Arc<RwLock<FunctionPrecedence>>,
     #[cfg(not(target_arch = "wasm32"))]
     pub file_map: Arc<RwLock<HashMap<PathBuf, Vec<String>>>>,
+    /// Problems found in each scanned custom function file, keyed like `file_map`.
+    #[cfg(not(target_arch = "wasm32"))]
+    custom_diagnostics: Arc<RwLock<HashMap<PathBuf, Vec<crate::parser::Diagnostic>>>>,
 }
 
 impl MetadataManager {
@@ -508,14 +732,18 @@ impl MetadataManager {
         cache_dir: impl Into<PathBuf>,
         fetch_urls: Vec<String>,
         client: Option<LspClient>,
+        cache_ttl: Option<Duration>,
     ) -> Result<Self> {
         Ok(Self {
-            fetcher: Fetcher::new(cache_dir, client),
+            fetcher: Fetcher::new(cache_dir, client, cache_ttl),
             fetch_urls,
             trie: Arc::new(RwLock::new(FunctionTrie::default())),
             enums: Arc::new(RwLock::new(HashMap::new())),
             events: Arc::new(RwLock::new(Vec::new())),
+            conflicts: Arc::new(RwLock::new(HashMap::new())),
+            precedence: Arc::new(RwLock::new(FunctionPrecedence::default())),
             file_map: Arc::new(RwLock::new(HashMap::new())),
+            custom_diagnostics: Arc::new(RwLock::new(HashMap::new())),
         })
     }
 
@@ -528,49 +756,217 @@ impl MetadataManager {
             trie: Arc::new(RwLock::new(FunctionTrie::default())),
             enums: Arc::new(RwLock::new(HashMap::new())),
             events: Arc::new(RwLock::new(Vec::new())),
+            conflicts: Arc::new(RwLock::new(HashMap::new())),
+            precedence: Arc::new(RwLock::new(FunctionPrecedence::default())),
         })
     }
 
     /// Triggers a refresh of all metadata from the configured source URLs.
+    #[cfg(target_arch = "wasm32")]
     pub async fn load_all(&self) -> Result<()> {
+        self.load_all_with_progress(&|_, _, _| {}).await
+    }
+
+    /// Refreshes all metadata, reporting `(url, completed, total)` as each source settles.
+    pub async fn load_all_with_progress(&self, progress: SourceProgress<'_>) -> Result<()> {
         crate::utils::forge_log(
             crate::utils::LogLevel::Info,
             "Refreshing all metadata sources...",
         );
         let start = crate::utils::Instant::now();
-        let mut all_funcs_map = self.fetcher.fetch_all(&self.fetch_urls).await?;
+        let (enum_urls, event_urls) = self.companion_urls();
+        let total = self.fetch_urls.len() + enum_urls.len() + event_urls.len();
+        let completed = AtomicUsize::new(0);
+        let on_loaded = |url: &str| {
+            let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
+            progress(url, done, total);
+        };
 
-        {
-            let mut trie = self
-                .trie
-                .write()
-                .expect("MetadataManager: trie lock poisoned");
-            for url in &self.fetch_urls {
-                if let Some(funcs) = all_funcs_map.get_mut(url) {
-                    let extension = if url.contains("githubusercontent.com") {
-                        url.split('/').nth(4).map(|s| s.to_string())
-                    } else {
-                        None
-                    };
+        let all_funcs_map = self.fetcher.fetch_all(&self.fetch_urls, &on_loaded).await?;
+        self.index_functions(all_funcs_map);
 
-                    for func in funcs {
-                        func.extension = extension.clone();
-                        func.source_url = Some(url.clone());
+        *self
+            .enums
+            .write()
+            .expect("MetadataManager: enums lock poisoned") =
+            self.fetcher.fetch_all_enums(&enum_urls, &on_loaded).await?;
+        *self
+            .events
+            .write()
+            .expect("MetadataManager: events lock poisoned") = self
+            .fetcher
+            .fetch_all_events(&event_urls, &on_loaded)
+            .await?;
 
-                        if let Some(aliases) = &func.aliases {
-                            for alias in aliases {
-                                let mut alias_func = func.clone();
-                                alias_func.name = alias.clone();
-                                trie.insert(alias, Arc::new(alias_func));
-                            }
+        crate::utils::forge_log(
+            crate::utils::LogLevel::Info,
+            &format!("Metadata refresh complete in {}", start.elapsed_display()),
+        );
+        Ok(())
+    }
+
+    /// Populates the index from the on-disk cache only, without any network access.
+    ///
+    /// Returns the number of functions indexed.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_from_cache(&self) -> usize {
+        let cached = self
+            .fetch_urls
+            .iter()
+            .filter_map(|url| {
+                let funcs = self.fetcher.read_cached::<Vec<Function>>(url)?;
+                Some((url.clone(), funcs))
+            })
+            .collect();
+        self.index_functions(cached);
+
+        let (enum_urls, event_urls) = self.companion_urls();
+        let mut enums = HashMap::new();
+        for url in &enum_urls {
+            if let Some(cached) = self
+                .fetcher
+                .read_cached::<HashMap<String, Vec<String>>>(url)
+            {
+                enums.extend(cached);
+            }
+        }
+        let mut events = Vec::new();
+        for url in &event_urls {
+            if let Some(cached) = self.fetcher.read_cached::<Vec<Event>>(url) {
+                events.extend(cached);
+            }
+        }
+        *self
+            .enums
+            .write()
+            .expect("MetadataManager: enums lock poisoned") = enums;
+        *self
+            .events
+            .write()
+            .expect("MetadataManager: events lock poisoned") = events;
+
+        self.function_count()
+    }
+
+    /// Inserts fetched functions (and their aliases) into the trie, tagging each with its source.
+    fn index_functions(&self, mut all_funcs_map: HashMap<String, Vec<Function>>) {
+        let mut trie = self
+            .trie
+            .write()
+            .expect("MetadataManager: trie lock poisoned");
+        for url in &self.fetch_urls {
+            if let Some(funcs) = all_funcs_map.get_mut(url) {
+                let extension = if url.contains("githubusercontent.com") {
+                    url.split('/').nth(4).map(|s| s.to_string())
+                } else {
+                    None
+                };
+
+                for func in funcs {
+                    func.extension = extension.clone();
+                    func.source_url = Some(url.clone());
+
+                    if let Some(aliases) = &func.aliases {
+                        for alias in aliases {
+                            let mut alias_func = func.clone();
+                            alias_func.name = alias.clone();
+                            self.insert_tracked(&mut trie, alias, Arc::new(alias_func));
                         }
-                        let name = func.name.clone();
-                        trie.insert(&name, Arc::new(func.clone()));
                     }
+                    let name = func.name.clone();
+                    self.insert_tracked(&mut trie, &name, Arc::new(func.clone()));
                 }
             }
         }
+    }
+
+    /// Inserts a metadata function, recording a conflict when another source already owns the name.
+    ///
+    /// Later sources win, matching the order of the configured URLs.
+    fn insert_tracked(&self, trie: &mut FunctionTrie, key: &str, func: Arc<Function>) {
+        if let Some(existing) = trie.get_exact(key)
+            && origin_of(&existing) != origin_of(&func)
+        {
+            let conflict = FunctionConflict {
+                name: key.to_string(),
+                kept: func.clone(),
+                shadowed: existing,
+            };
+            crate::utils::forge_log(crate::utils::LogLevel::Warn, &conflict.describe());
+            self.conflicts
+                .write()
+                .expect("MetadataManager: lock poisoned")
+                .insert(key.to_lowercase(), conflict);
+        }
+        trie.insert(key, func);
+    }
+
+    /// Registers a custom function under `key`, applying the precedence policy on collisions.
+    ///
+    /// Returns `false` when a built-in function keeps the name instead.
+    fn insert_custom(&self, trie: &mut FunctionTrie, key: &str, func: Arc<Function>) -> bool {
+        let precedence = *self
+            .precedence
+            .read()
+            .expect("MetadataManager: lock poisoned");
+        let mut conflicts = self
+            .conflicts
+            .write()
+            .expect("MetadataManager: lock poisoned");
+        let lower = key.to_lowercase();
+
+        match trie.get_exact(key) {
+            Some(existing) if !is_custom(&existing) => {
+                let (kept, shadowed) = match precedence {
+                    FunctionPrecedence::Custom => (func.clone(), existing),
+                    FunctionPrecedence::Builtin => (existing, func.clone()),
+                };
+                let conflict = FunctionConflict {
+                    name: key.to_string(),
+                    kept,
+                    shadowed,
+                };
+                crate::utils::forge_log(crate::utils::LogLevel::Warn, &conflict.describe());
+                conflicts.insert(lower, conflict);
+                if precedence == FunctionPrecedence::Builtin {
+                    return false;
+                }
+            }
+            // A custom function replacing another custom override keeps the built-in shadowed.
+            Some(_) => {
+                if let Some(conflict) = conflicts.get_mut(&lower)
+                    && is_custom(&conflict.kept)
+                {
+                    conflict.kept = func.clone();
+                }
+            }
+            None => {}
+        }
+        trie.insert(key, func);
+        true
+    }
+
+    /// Returns every recorded name conflict.
+    pub fn conflicts(&self) -> Vec<FunctionConflict> {
+        self.conflicts
+            .read()
+            .expect("MetadataManager: lock poisoned")
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// Returns the recorded collision for a name or alias, if any.
+    pub fn conflict_for(&self, name: &str) -> Option<FunctionConflict> {
+        self.conflicts
+            .read()
+            .expect("MetadataManager: lock poisoned")
+            .get(&name.to_lowercase())
+            .cloned()
+    }
 
+    /// Derives the `enums.json` and `events.json` URLs that accompany each `functions.json` source.
+    fn companion_urls(&self) -> (Vec<String>, Vec<String>) {
         let mut enum_urls = Vec::new();
         let mut event_urls = Vec::new();
         for url in &self.fetch_urls {
@@ -579,44 +975,58 @@ impl MetadataManager {
                 event_urls.push(url.replace("functions.json", "events.json"));
             }
         }
-
-        *self
-            .enums
-            .write()
-            .expect("MetadataManager: enums lock poisoned") =
-            self.fetcher.fetch_all_enums(&enum_urls).await?;
-        *self
-            .events
-            .write()
-            .expect("MetadataManager: events lock poisoned") =
-            self.fetcher.fetch_all_events(&event_urls).await?;
-
-        crate::utils::forge_log(
-            crate::utils::LogLevel::Info,
-            &format!("Metadata refresh complete in {}", start.elapsed_display()),
-        );
-        Ok(())
+        (enum_urls, event_urls)
     }
 
     /// Ingests custom functions from inline configuration or file paths.
+    ///
+    /// Returns the number of files scanned and the number of functions registered.
     #[cfg(not(target_arch = "wasm32"))]
     pub fn load_custom_functions_from_config(
         &self,
         config: &crate::utils::ForgeConfig,
         config_dir: &Path,
-    ) -> Result<()> {
+    ) -> Result<(usize, usize)> {
+        self.load_custom_functions_from_config_with_progress(config, config_dir, &|_, _, _| {})
+    }
+
+    /// Loads custom functions from the configuration, reporting `(path, completed, total)`
+    /// as each JS/TS file is parsed.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load_custom_functions_from_config_with_progress(
+        &self,
+        config: &crate::utils::ForgeConfig,
+        config_dir: &Path,
+        progress: FileProgress<'_>,
+    ) -> Result<(usize, usize)> {
+        *self
+            .precedence
+            .write()
+            .expect("MetadataManager: lock poisoned") =
+            config.function_precedence.unwrap_or_default();
+        let mut files = 0;
+        let mut count = 0;
         if let Some(funcs) = &config.custom_functions
             && !funcs.is_empty()
         {
-            self.add_custom_functions(funcs.clone())?;
+            count += funcs.len();
+            let names = self.add_custom_functions(funcs.clone())?;
+            // Inline definitions are tracked under the config file so a reload can replace them.
+            self.file_map
+                .write()
+                .expect("MetadataManager: file_map lock poisoned")
+                .insert(config_dir.join("forgeconfig.json"), names);
         }
         if let Some(custom_path) = &config.custom_functions_path {
             let full_path = config_dir.join(custom_path);
             if full_path.exists() {
-                self.load_custom_functions_from_folder(full_path)?;
+                let (found, loaded) =
+                    self.load_custom_functions_from_folder(full_path, progress)?;
+                files += found.len();
+                count += loaded;
             }
         }
-        Ok(())
+        Ok((files, count))
     }
 
     /// Recursively scans a directory for custom function definitions in JS/TS files.
@@ -624,271 +1034,294 @@ impl MetadataManager {
     pub fn load_custom_functions_from_folder(
         &self,
         path: PathBuf,
+        progress: FileProgress<'_>,
     ) -> Result<(Vec<PathBuf>, usize)> {
         if !path.exists() || !path.is_dir() {
             return Ok((Vec::new(), 0));
         }
-        let mut custom_funcs = Vec::new();
         let mut files_found = Vec::new();
-        self.scan_recursive(&path, &mut custom_funcs, &mut files_found)?;
-        Ok((files_found, custom_funcs.len()))
+        Self::scan_recursive(&path, &mut files_found)?;
+        let mut loaded = 0;
+        for (done, file) in files_found.iter().enumerate() {
+            loaded += self.load_js_file(file)?.len();
+            progress(file, done + 1, files_found.len());
+        }
+        Ok((files_found, loaded))
     }
 
+    /// Collects every JS/TS file below `path`.
     #[cfg(not(target_arch = "wasm32"))]
-    fn scan_recursive(
-        &self,
-        path: &Path,
-        funcs: &mut Vec<crate::utils::CustomFunction>,
-        files: &mut Vec<PathBuf>,
-    ) -> Result<()> {
+    fn scan_recursive(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
         for entry in fs::read_dir(path)? {
             let path = entry?.path();
             if path.is_dir() {
-                self.scan_recursive(&path, funcs, files)?;
+                Self::scan_recursive(&path, files)?;
             } else if path.is_file()
                 && let Some(_ext) = path.extension().filter(|&e| e == "js" || e == "ts")
             {
-                let content = fs::read_to_string(&path)?;
-                let parsed = self
-                    .parse_custom_functions_from_js(&content, path.to_str().unwrap_or_default());
-                let names = self.add_custom_functions(parsed.clone())?;
-                self.file_map
-                    .write()
-                    .expect("MetadataManager: file_map lock poisoned")
-                    .insert(path.clone(), names);
-                funcs.extend(parsed);
                 files.push(path);
             }
         }
         Ok(())
     }
 
-    /// Unregisters all functions associated with a specific file path.
+    /// Replaces every custom function with the definitions from the given configuration.
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn remove_functions_at_path(&self, path: &Path) {
-        if let Some(names) = self
-            .file_map
-            .write()
-            .expect("MetadataManager: lock poisoned")
-            .remove(path)
-        {
-            let mut trie = self.trie.write().expect("MetadataManager: lock poisoned");
-            for name in names {
-                trie.remove(&name);
-            }
-        }
+    pub fn reload_custom_functions_from_config(
+        &self,
+        config: &crate::utils::ForgeConfig,
+        config_dir: &Path,
+    ) -> Result<(usize, usize)> {
+        self.clear_custom_functions();
+        self.load_custom_functions_from_config(config, config_dir)
     }
 
-    /// Forces a reload of custom functions from a modified file.
+    /// Unregisters every custom function and restores the built-ins they were shadowing.
     #[cfg(not(target_arch = "wasm32"))]
-    pub fn reload_file(&self, path: PathBuf) -> Result<usize> {
-        if !path.exists() || !path.is_file() {
+    pub fn clear_custom_functions(&self) {
+        let paths: Vec<PathBuf> = self
+            .file_map
+            .read()
+            .expect("MetadataManager: file_map lock poisoned")
+            .keys()
+            .cloned()
+            .collect();
+        for path in paths {
             self.remove_functions_at_path(&path);
-            return Ok(0);
         }
-        let content = fs::read_to_string(&path)?;
-        let parsed =
-            self.parse_custom_functions_from_js(&content, path.to_str().unwrap_or_default());
-        self.remove_functions_at_path(&path);
-        let count = parsed.len();
-        let names = self.add_custom_functions(parsed)?;
-        self.file_map
+        self.conflicts
             .write()
             .expect("MetadataManager: lock poisoned")
-            .insert(path, names);
-        Ok(count)
+            .retain(|_, c| !is_custom(&c.kept) && !is_custom(&c.shadowed));
+        *self
+            .precedence
+            .write()
+            .expect("MetadataManager: lock poisoned") = FunctionPrecedence::default();
     }
 
-    /// Parses function headers and metadata from JS/TS source code using regular expressions.
+    /// Parses a JS/TS file, registers its named functions and records problems in its definitions.
     #[cfg(not(target_arch = "wasm32"))]
-    fn parse_custom_functions_from_js(
-        &self,
-        content: &str,
-        file_path: &str,
-    ) -> Vec<crate::utils::CustomFunction> {
-        let mut functions = Vec::new();
-
-        let name_re = regex::Regex::new(r#"name:\s*['"]([^'"]+)['"]"#)
-            .expect("MetadataManager: regex failure");
-        let name_matches: Vec<_> = name_re
-            .captures_iter(content)
-            .map(|c| {
-                let m = c.get(0).unwrap();
-                let start = m.start();
-                let line = content[..start].chars().filter(|&c| c == '\n').count() as u32;
-                (start, m.end(), c[1].to_string(), line)
+    fn load_js_file(&self, path: &Path) -> Result<Vec<crate::utils::CustomFunction>> {
+        let content = fs::read_to_string(path)?;
+        let extracted =
+            crate::custom_js::extract_custom_functions(&content, path.to_str().unwrap_or_default());
+        let mut diagnostics =
+            crate::custom_js::check_definitions(&content, &extracted, &self.known_arg_types());
+
+        // Names registered by other files would be silently shadowed by this one.
+        {
+            let file_map = self
+                .file_map
+                .read()
+                .expect("MetadataManager: file_map lock poisoned");
+            for func in &extracted {
+                let Some((start, end)) = func.name_span else {
+                    continue;
+                };
+                let name = crate::custom_js::normalized_name(&func.function.name);
+                if let Some(other) = file_map
+                    .iter()
+                    .find(|(other, names)| other.as_path() != path && names.contains(&name))
+                    .map(|(other, _)| other)
+                {
+                    diagnostics.push(crate::parser::Diagnostic {
+                        message: format!(
+                            "Custom function `{name}` is already defined in {}",
+                            other.display()
+                        ),
+                        start,
+                        end,
+                        severity: crate::parser::Severity::Warning,
+                        code: None,
+                    });
+                }
+            }
+        }
+
+        let extracted_spans: Vec<_> = extracted
+            .iter()
+            .filter_map(|f| {
+                let span = f.name_span?;
+                Some((crate::custom_js::normalized_name(&f.function.name), span))
             })
             .collect();
+        let parsed: Vec<_> = extracted
+            .into_iter()
+            .filter(|f| f.name_span.is_some())
+            .map(|f| f.function)
+            .collect();
+        let names = self.add_custom_functions(parsed.clone())?;
+        self.file_map
+            .write()
+            .expect("MetadataManager: file_map lock poisoned")
+            .insert(path.to_path_buf(), names);
 
-        let params_start_re =
-            regex::Regex::new(r#"(?:params|args):\s*\["#).expect("MetadataManager: regex failure");
-        let mut params_ranges = Vec::new();
-        for m in params_start_re.find_iter(content) {
-            let start = m.start();
-            let mut depth = 0;
-            for (i, c) in content[start..].char_indices() {
-                if c == '[' {
-                    depth += 1;
-                } else if c == ']' {
-                    depth -= 1;
-                    if depth == 0 {
-                        params_ranges.push(start..start + i);
-                        break;
-                    }
-                }
+        // Collisions with built-in functions are resolved by `function_precedence`.
+        for (name, (start, end)) in &extracted_spans {
+            let Some(conflict) = self.conflict_for(name) else {
+                continue;
+            };
+            if conflict.kept.local_path.as_deref() != Some(path)
+                && conflict.shadowed.local_path.as_deref() != Some(path)
+            {
+                continue;
             }
+            diagnostics.push(crate::parser::Diagnostic {
+                message: conflict.custom_message(),
+                start: *start,
+                end: *end,
+                severity: crate::parser::Severity::Warning,
+                code: None,
+            });
         }
 
-        let mut filtered_names = Vec::new();
-        for m in &name_matches {
-            if !params_ranges.iter().any(|r| r.contains(&m.0)) {
-                filtered_names.push(m.clone());
+        self.custom_diagnostics
+            .write()
+            .expect("MetadataManager: lock poisoned")
+            .insert(path.to_path_buf(), diagnostics);
+        Ok(parsed)
+    }
+
+    /// Returns the definition problems of every scanned custom function file.
+    ///
+    /// Files whose problems were resolved or which were removed report an empty list,
+    /// so that stale diagnostics can be cleared.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn custom_function_diagnostics(&self) -> Vec<(PathBuf, Vec<crate::parser::Diagnostic>)> {
+        self.custom_diagnostics
+            .read()
+            .expect("MetadataManager: lock poisoned")
+            .iter()
+            .map(|(path, diags)| (path.clone(), diags.clone()))
+            .collect()
+    }
+
+    /// Returns the definition problems recorded for one scanned custom function file.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn custom_diagnostics_for(&self, path: &Path) -> Option<Vec<crate::parser::Diagnostic>> {
+        self.custom_diagnostics
+            .read()
+            .expect("MetadataManager: lock poisoned")
+            .get(path)
+            .cloned()
+    }
+
+    /// Collects the argument types used by the loaded (non-custom) function metadata.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn known_arg_types(&self) -> std::collections::HashSet<String> {
+        self.all_functions()
+            .iter()
+            .filter(|f| f.category.as_deref() != Some("custom"))
+            .flat_map(|f| f.args.iter().flatten())
+            .flat_map(|arg| match &arg.arg_type {
+                JsonValue::String(s) => vec![s.clone()],
+                JsonValue::Array(types) => types
+                    .iter()
+                    .filter_map(|t| t.as_str().map(str::to_string))
+                    .collect(),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Unregisters all functions associated with a specific file path.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn remove_functions_at_path(&self, path: &Path) {
+        if let Some(diags) = self
+            .custom_diagnostics
+            .write()
+            .expect("MetadataManager: lock poisoned")
+            .get_mut(path)
+        {
+            diags.clear();
+        }
+        let mut file_map = self
+            .file_map
+            .write()
+            .expect("MetadataManager: lock poisoned");
+        let Some(names) = file_map.remove(path) else {
+            return;
+        };
+        // Names another file still defines go back to that file's definition.
+        let mut still_defined: HashMap<PathBuf, Vec<String>> = HashMap::new();
+        let mut orphaned = Vec::new();
+        for name in names {
+            match file_map.iter().find(|(_, other)| other.contains(&name)) {
+                Some((owner, _)) => still_defined.entry(owner.clone()).or_default().push(name),
+                None => orphaned.push(name),
             }
         }
+        drop(file_map);
 
-        let desc_re = regex::Regex::new(r#"(?s)description:\s*(?:'((?:[^'\\]|\\.)*?)'|"((?:[^"\\]|\\.)*?)"|`((?:[^`\\]|\\.)*?)`)"#).expect("MetadataManager: regex failure");
-        let brackets_re =
-            regex::Regex::new(r"brackets:\s*(true|false)").expect("MetadataManager: regex failure");
-        let p_name_re = regex::Regex::new(r#"name:\s*['"]([^'"]+)['"]"#)
-            .expect("MetadataManager: regex failure");
-        let required_re = regex::Regex::new(r"(?i)required:\s*(true|false)")
-            .expect("MetadataManager: regex failure");
-        let rest_re =
-            regex::Regex::new(r"(?i)rest:\s*(true|false)").expect("MetadataManager: regex failure");
-        let type_re =
-            regex::Regex::new(r"type:\s*([^,}\n\s]+)").expect("MetadataManager: regex failure");
-        let output_re =
-            regex::Regex::new(r"output:\s*([^,}\n\s]+)").expect("MetadataManager: regex failure");
-
-        for i in 0..filtered_names.len() {
-            let (_, end_pos, name, line) = &filtered_names[i];
-            let chunk_end = if i + 1 < filtered_names.len() {
-                filtered_names[i + 1].0
-            } else {
-                content.len()
-            };
-            let chunk = &content[*end_pos..chunk_end];
-
-            let description = desc_re.captures(chunk).map(|c| {
-                c.get(1)
-                    .or(c.get(2))
-                    .or(c.get(3))
-                    .unwrap()
-                    .as_str()
-                    .to_string()
-            });
-            let brackets = brackets_re.captures(chunk).map(|c| &c[1] == "true");
-            let output = output_re.captures(chunk).map(|c| {
-                c[1].split(',')
-                    .map(|s| {
-                        s.trim()
-                            .trim_matches(|c| c == '\'' || c == '\"')
-                            .to_string()
-                    })
-                    .filter(|s| !s.is_empty())
-                    .collect()
-            });
+        for (owner, names) in still_defined {
+            let restored = self.restore_definitions(&owner, &names);
+            orphaned.extend(names.into_iter().filter(|name| !restored.contains(name)));
+        }
 
-            let mut params = None;
-            if let Some(p_range) = params_ranges
-                .iter()
-                .find(|r| r.start >= *end_pos && r.start < chunk_end)
+        let mut trie = self.trie.write().expect("MetadataManager: lock poisoned");
+        let mut conflicts = self
+            .conflicts
+            .write()
+            .expect("MetadataManager: lock poisoned");
+        for name in orphaned {
+            trie.remove(&name);
+            // Bring back the built-in this custom function was overriding.
+            let lower = name.to_lowercase();
+            if let Some(conflict) = conflicts.get(&lower)
+                && is_custom(&conflict.kept)
+                && !is_custom(&conflict.shadowed)
             {
-                let p_content = &content[p_range.clone()];
-                let mut param_objects = Vec::new();
-                let mut search_idx = 0;
-                while let Some(start_bracket) = p_content[search_idx..].find('{') {
-                    let abs_start = search_idx + start_bracket;
-                    let mut depth = 0;
-                    for (i, c) in p_content[abs_start..].char_indices() {
-                        if c == '{' {
-                            depth += 1;
-                        } else if c == '}' {
-                            depth -= 1;
-                            if depth == 0 {
-                                let obj_body = &p_content[abs_start + 1..abs_start + i];
-                                let mut obj_map = serde_json::Map::new();
-                                if let Some(n_cap) = p_name_re.captures(obj_body) {
-                                    obj_map.insert(
-                                        "name".to_string(),
-                                        JsonValue::String(n_cap[1].to_string()),
-                                    );
-                                    if let Some(r_cap) = required_re.captures(obj_body) {
-                                        obj_map.insert(
-                                            "required".to_string(),
-                                            JsonValue::Bool(&r_cap[1] == "true"),
-                                        );
-                                    }
-                                    if let Some(rest_cap) = rest_re.captures(obj_body) {
-                                        obj_map.insert(
-                                            "rest".to_string(),
-                                            JsonValue::Bool(&rest_cap[1] == "true"),
-                                        );
-                                    }
-                                    let t_val = type_re
-                                        .captures(obj_body)
-                                        .map(|c| {
-                                            c[1].trim()
-                                                .trim_matches(|c| c == '\'' || c == '\"')
-                                                .to_string()
-                                        })
-                                        .unwrap_or_else(|| "String".into());
-                                    obj_map.insert(
-                                        "type".to_string(),
-                                        JsonValue::String(
-                                            t_val
-                                                .strip_prefix("ArgType.")
-                                                .unwrap_or(&t_val)
-                                                .to_string(),
-                                        ),
-                                    );
-                                    if let Some(d_cap) = desc_re.captures(obj_body) {
-                                        obj_map.insert(
-                                            "description".to_string(),
-                                            JsonValue::String(
-                                                d_cap
-                                                    .get(1)
-                                                    .or(d_cap.get(2))
-                                                    .or(d_cap.get(3))
-                                                    .unwrap()
-                                                    .as_str()
-                                                    .to_string(),
-                                            ),
-                                        );
-                                    }
-                                    param_objects.push(JsonValue::Object(obj_map));
-                                }
-                                search_idx = abs_start + i + 1;
-                                break;
-                            }
-                        }
-                    }
-                }
-                if !param_objects.is_empty() {
-                    params = Some(JsonValue::Array(param_objects));
-                } else {
-                    let names: Vec<JsonValue> = p_name_re
-                        .captures_iter(p_content)
-                        .map(|c| JsonValue::String(c[1].to_string()))
-                        .collect();
-                    if !names.is_empty() {
-                        params = Some(JsonValue::Array(names));
-                    }
-                }
+                trie.insert(&name, conflict.shadowed.clone());
+                conflicts.remove(&lower);
             }
+        }
+        conflicts.retain(|_, c| {
+            !(is_custom(&c.shadowed) && c.shadowed.local_path.as_deref() == Some(path))
+        });
+    }
 
-            functions.push(crate::utils::CustomFunction {
-                name: name.clone(),
-                description,
-                params,
-                brackets,
-                alias: None,
-                path: Some(file_path.to_string()),
-                line: Some(*line),
-                output,
-            });
+    /// Registers `owner`'s own definitions of `names` again, returning the names restored.
+    ///
+    /// `owner` is either a scanned JS/TS file or a `forgeconfig.json` with inline functions.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn restore_definitions(&self, owner: &Path, names: &[String]) -> Vec<String> {
+        let Ok(content) = fs::read_to_string(owner) else {
+            return Vec::new();
+        };
+        let definitions = if owner
+            .file_name()
+            .is_some_and(|name| name == crate::config_schema::CONFIG_FILE_NAME)
+        {
+            crate::utils::parse_forge_config(&content)
+                .and_then(|config| config.custom_functions)
+                .unwrap_or_default()
+        } else {
+            crate::custom_js::extract_custom_functions(&content, owner.to_str().unwrap_or_default())
+                .into_iter()
+                .filter(|f| f.name_span.is_some())
+                .map(|f| f.function)
+                .collect()
+        };
+        let wanted = definitions
+            .into_iter()
+            .filter(|f| {
+                std::iter::once(&f.name)
+                    .chain(f.alias.iter().flatten())
+                    .any(|name| names.contains(&crate::custom_js::normalized_name(name)))
+            })
+            .collect();
+        self.add_custom_functions(wanted).unwrap_or_default()
+    }
+
+    /// Forces a reload of custom functions from a modified file.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn reload_file(&self, path: PathBuf) -> Result<usize> {
+        if !path.exists() || !path.is_file() {
+            self.remove_functions_at_path(&path);
+            return Ok(0);
         }
-        functions
+        self.remove_functions_at_path(&path);
+        Ok(self.load_js_file(&path)?.len())
     }
 
     /// Registers a list of custom function definitions.
@@ -983,18 +1416,20 @@ impl MetadataManager {
                 source_url: None,
                 local_path: custom.path.as_ref().map(PathBuf::from),
                 line: custom.line,
+                column: custom.column,
             };
 
-            let arc_func = Arc::new(func.clone());
-            trie.insert(&name, arc_func);
-            registered_names.push(name.clone());
+            if self.insert_custom(&mut trie, &name, Arc::new(func.clone())) {
+                registered_names.push(name.clone());
+            }
 
             if let Some(aliases) = &func.aliases {
                 for alias in aliases {
                     let mut alias_func = func.clone();
                     alias_func.name = alias.clone();
-                    trie.insert(alias, Arc::new(alias_func));
-                    registered_names.push(alias.clone());
+                    if self.insert_custom(&mut trie, alias, Arc::new(alias_func)) {
+                        registered_names.push(alias.clone());
+                    }
                 }
             }
         }
@@ -1022,6 +1457,11 @@ impl MetadataManager {
             .get_exact(name)
     }
 
+    /// Returns the number of configured function metadata sources.
+    pub fn source_count(&self) -> usize {
+        self.fetch_urls.len()
+    }
+
     /// Returns the total number of functions managed.
     pub fn function_count(&self) -> usize {
         self.trie
@@ -1038,3 +1478,40 @@ impl MetadataManager {
             .collect_all()
     }
 }
+
+/// Builds a manager serving only the given `(name, params)` functions as built-ins.
+///
+/// A parameter name starting with `?` marks a condition argument.
+#[cfg(test)]
+pub(crate) fn test_manager(functions: &[(&str, &[&str])]) -> Arc<MetadataManager> {
+    const SOURCE: &str = "test://functions.json";
+    let manager = MetadataManager::new(
+        std::env::temp_dir().join("forgevsc-test-cache"),
+        vec![SOURCE.to_string()],
+        None,
+        None,
+    )
+    .expect("MetadataManager: test manager");
+    let functions = functions
+        .iter()
+        .map(|(name, params)| Function {
+            name: format!("${name}"),
+            brackets: (!params.is_empty()).then_some(true),
+            args: (!params.is_empty()).then(|| {
+                params
+                    .iter()
+                    .map(|param| Arg {
+                        name: param.trim_start_matches('?').to_string(),
+                        required: Some(true),
+                        arg_type: JsonValue::from("String"),
+                        condition: param.starts_with('?').then_some(true),
+                        ..Default::default()
+                    })
+                    .collect()
+            }),
+            ..Default::default()
+        })
+        .collect();
+    manager.index_functions(HashMap::from([(SOURCE.to_string(), functions)]));
+    Arc::new(manager)
+}
@@ -16,12 +16,26 @@ use std::sync::Arc;
 /// Functions that bypass enum validation for specific arguments.
 const ENUM_VALIDATION_EXCEPTIONS: &[(&str, usize); 1] = &[("color", 0)];
 
+/// How serious a diagnostic is when reported to the client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    #[default]
+    Error,
+    Warning,
+}
+
 /// Captures syntax errors or warnings during the parsing phase.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Diagnostic {
     pub message: String,
     pub start: usize,
     pub end: usize,
+    #[serde(default)]
+    pub severity: Severity,
+    /// Stable identifier of an optional check, used to suppress it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
 }
 
 /// Token types recognized by the ForgeScript scanner.
@@ -61,19 +75,38 @@ pub struct ParsedFunction {
     pub count: Option<usize>,
     #[allow(dead_code)]
     pub meta: Arc<Function>,
+    /// Number of calls enclosing this one, only set on the calls in `ParseResult::functions`.
+    pub depth: usize,
 }
 
 impl ParsedFunction {
     /// Adjusts the byte offsets for this function and its arguments.
     pub fn offset_spans(&mut self, offset: usize) {
-        self.span.0 += offset;
-        self.span.1 += offset;
+        self.map_spans(&|pos| pos + offset);
+    }
+
+    /// Returns the offsets of the `;` separating this call's arguments in `source`.
+    pub fn separator_offsets(&self, source: &str) -> Vec<usize> {
+        let Some(args) = &self.args else {
+            return Vec::new();
+        };
+        args.windows(2)
+            .filter_map(|pair| {
+                let (end, next_start) = (pair[0].1.1, pair[1].1.0);
+                let sep = source.get(end..next_start)?.find(';')?;
+                Some(end + sep)
+            })
+            .collect()
+    }
+
+    /// Rewrites every byte offset of this function and its arguments.
+    pub fn map_spans(&mut self, map: &impl Fn(usize) -> usize) {
+        self.span = (map(self.span.0), map(self.span.1));
         if let Some(args) = &mut self.args {
             for (arg_parts, span) in args {
-                span.0 += offset;
-                span.1 += offset;
+                *span = (map(span.0), map(span.1));
                 for part in arg_parts {
-                    part.offset_spans(offset);
+                    part.map_spans(map);
                 }
             }
         }
@@ -85,6 +118,7 @@ impl ParsedFunction {
 pub struct ParseResult {
     pub tokens: Vec<Token>,
     pub diagnostics: Vec<Diagnostic>,
+    /// Every call, sorted by span so enclosing calls come before the calls nested in them.
     pub functions: Vec<ParsedFunction>,
 }
 
@@ -96,9 +130,9 @@ pub enum ParsedArg {
 }
 
 impl ParsedArg {
-    pub fn offset_spans(&mut self, offset: usize) {
+    pub fn map_spans(&mut self, map: &impl Fn(usize) -> usize) {
         if let ParsedArg::Function { func } = self {
-            func.offset_spans(offset);
+            func.map_spans(map);
         }
     }
 }
@@ -108,7 +142,8 @@ fn map_to_block(position: usize, block_lengths: &[usize]) -> (usize, usize) {
     let mut current_pos = 0;
     for (idx, &length) in block_lengths.iter().enumerate() {
         let block_end = current_pos + length;
-        if position < block_end {
+        // An exclusive end offset may sit exactly on the block end.
+        if position <= block_end {
             return (idx, position - current_pos);
         }
         current_pos = block_end + 1; // Includes newline separator
@@ -134,6 +169,45 @@ fn is_ignore_error_directive(code: &str, dollar_idx: usize) -> Option<usize> {
     }
 }
 
+/// Returns the byte ranges of the contents of every ``code: `...` `` template in a file.
+pub fn code_blocks(code: &str) -> Vec<(usize, usize)> {
+    let bytes = code.as_bytes();
+    let mut blocks = Vec::new();
+    let mut i = 0;
+
+    while i < code.len() {
+        if i + 5 <= code.len() && &bytes[i..i + 5] == b"code:" {
+            let mut j = i + 5;
+            while j < bytes.len() && bytes[j].is_ascii_whitespace() {
+                j += 1;
+            }
+            if j < bytes.len() && bytes[j] == b'`' {
+                j += 1;
+                let content_start = j;
+                let mut found_end = false;
+                while j < bytes.len() {
+                    if bytes[j] == b'\\' && j + 1 < bytes.len() {
+                        j += 2;
+                        continue;
+                    }
+                    if bytes[j] == b'`' {
+                        found_end = true;
+                        break;
+                    }
+                    j += 1;
+                }
+                if found_end {
+                    blocks.push((content_start, j));
+                    i = j + 1;
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+    blocks
+}
+
 /// Main parser for ForgeScript files, capable of extracting code blocks and parsing them.
 pub struct ForgeScriptParser<'a> {
     manager: Arc<MetadataManager>,
@@ -170,61 +244,45 @@ impl<'a> ForgeScriptParser<'a> {
             return self.parse_internal();
         }
 
+        let blocks = code_blocks(self.code);
         let mut code_to_parse = String::new();
         let mut offsets = Vec::new();
         let mut lengths = Vec::new();
-        let bytes = self.code.as_bytes();
-        let mut i = 0;
-
-        while i < self.code.len() {
-            if i + 5 <= self.code.len() && &bytes[i..i + 5] == b"code:" {
-                let mut j = i + 5;
-                while j < bytes.len() && bytes[j].is_ascii_whitespace() {
-                    j += 1;
-                }
-                if j < bytes.len() && bytes[j] == b'`' {
-                    j += 1;
-                    let content_start = j;
-                    let mut found_end = false;
-                    while j < bytes.len() {
-                        if bytes[j] == b'\\' && j + 1 < bytes.len() {
-                            j += 2;
-                            continue;
-                        }
-                        if bytes[j] == b'`' {
-                            found_end = true;
-                            break;
-                        }
-                        j += 1;
-                    }
-                    if found_end {
-                        let content = &self.code[content_start..j];
-                        offsets.push(content_start);
-                        lengths.push(content.len());
-                        code_to_parse.push_str(content);
-                        code_to_parse.push('\n');
-                        i = j + 1;
-                        continue;
-                    }
-                }
-            }
-            i += 1;
+        for &(start, end) in &blocks {
+            offsets.push(start);
+            lengths.push(end - start);
+            code_to_parse.push_str(&self.code[start..end]);
+            code_to_parse.push('\n');
         }
 
         if !offsets.is_empty() {
             let mut result =
                 ForgeScriptParser::new_internal(self.manager.clone(), &code_to_parse).parse();
+            let remap = |pos: usize| {
+                let (block, offset) = map_to_block(pos, &lengths);
+                offsets[block] + offset
+            };
             for diag in &mut result.diagnostics {
-                let (bi, bo) = map_to_block(diag.start, &lengths);
-                diag.start = offsets[bi] + bo;
-                let (ei, eo) = map_to_block(diag.end, &lengths);
-                diag.end = offsets[ei] + eo;
+                diag.start = remap(diag.start);
+                diag.end = remap(diag.end);
+            }
+            for func in &mut result.functions {
+                func.map_spans(&remap);
+            }
+            for token in &mut result.tokens {
+                token.start = remap(token.start);
+                token.end = remap(token.end);
             }
+            result
+                .functions
+                .sort_by_key(|f| (f.span.0, std::cmp::Reverse(f.span.1)));
+            let mut open: Vec<usize> = Vec::new();
             for func in &mut result.functions {
-                let (bi, bo) = map_to_block(func.span.0, &lengths);
-                func.span.0 = offsets[bi] + bo;
-                let (ei, eo) = map_to_block(func.span.1, &lengths);
-                func.span.1 = offsets[ei] + eo;
+                while open.last().is_some_and(|&end| end <= func.span.0) {
+                    open.pop();
+                }
+                func.depth = open.len();
+                open.push(func.span.1);
             }
             return result;
         }
@@ -356,6 +414,8 @@ impl<'a> ForgeScriptParser<'a> {
                 end: self.code.len(),
             });
         }
+        // Argument tokens are collected before the name of the function holding them.
+        tokens.sort_by_key(|t| t.start);
         ParseResult {
             tokens,
             diagnostics,
@@ -425,7 +485,11 @@ impl<'a> ForgeScriptParser<'a> {
         diagnostics: &mut Vec<Diagnostic>,
         ignore: bool,
     ) -> bool {
-        if let Some(end) = find_matching_brace(self.code, brace_idx) {
+        if let Some((end, analysis)) = crate::embedded_js::analyze_expression(self.code, brace_idx)
+        {
+            if !ignore {
+                diagnostics.extend(analysis.diagnostics);
+            }
             tokens.push(Token {
                 kind: TokenKind::JavaScript,
                 text: self.code[brace_idx + 1..end].to_string(),
@@ -447,6 +511,8 @@ impl<'a> ForgeScriptParser<'a> {
                 message: "Unclosed JS expression".into(),
                 start,
                 end: self.code.len(),
+                severity: Severity::Error,
+                code: None,
             });
         }
         *last_idx = self.code.len();
@@ -490,6 +556,8 @@ impl<'a> ForgeScriptParser<'a> {
                     message: format!("Unclosed '[' for ${name}"),
                     start,
                     end: self.code.len(),
+                    severity: Severity::Error,
+                    code: None,
                 });
             }
             *last = self.code.len();
@@ -500,6 +568,8 @@ impl<'a> ForgeScriptParser<'a> {
                 message: format!("${name} requires brackets"),
                 start,
                 end,
+                severity: Severity::Error,
+                code: None,
             });
         }
         tokens.push(Token {
@@ -613,6 +683,8 @@ impl<'a> ForgeScriptParser<'a> {
                 message: format!("Unknown function `${name}`"),
                 start,
                 end: name_end,
+                severity: Severity::Error,
+                code: None,
             });
         }
         tokens.push(Token {
@@ -635,6 +707,7 @@ impl<'a> ForgeScriptParser<'a> {
                         silent: false,
                         negated: false,
                         count: None,
+                        depth: 0,
                         meta: Arc::new(Function {
                             name: format!("${name}"),
                             description: format!("Unknown function ${name}"),
@@ -689,6 +762,8 @@ impl<'a> ForgeScriptParser<'a> {
                     message: format!("Unclosed '[' for ${name}"),
                     start: idx,
                     end: self.code.len(),
+                    severity: Severity::Error,
+                    code: None,
                 });
                 tokens.push(Token {
                     kind: TokenKind::Text,
@@ -741,6 +816,8 @@ impl<'a> ForgeScriptParser<'a> {
                             message: format!("Unclosed '[' for ${name}"),
                             start,
                             end: self.code.len(),
+                            severity: Severity::Error,
+                            code: None,
                         });
                     }
                     *last = self.code.len();
@@ -757,7 +834,9 @@ impl<'a> ForgeScriptParser<'a> {
 
         if let Some(inner) = args_text {
             if meta.brackets.is_some() {
-                if let Ok(vec) = parse_nested_args(inner, &self.manager, diags, funcs, args_start) {
+                if let Ok(vec) =
+                    parse_nested_args(inner, &self.manager, diags, funcs, tokens, args_start)
+                {
                     parsed_args = Some(vec.clone());
                     validate_arg_count(
                         &name,
@@ -781,6 +860,8 @@ impl<'a> ForgeScriptParser<'a> {
                         message: format!("Failed to parse args for ${name}"),
                         start,
                         end: *last,
+                        severity: Severity::Error,
+                        code: None,
                     });
                 }
             } else if !ignore {
@@ -788,6 +869,8 @@ impl<'a> ForgeScriptParser<'a> {
                     message: format!("${name} does not accept brackets"),
                     start,
                     end: *last,
+                    severity: Severity::Error,
+                    code: None,
                 });
             }
         } else if meta.brackets == Some(true) && !ignore {
@@ -795,6 +878,8 @@ impl<'a> ForgeScriptParser<'a> {
                 message: format!("${name} expects brackets"),
                 start,
                 end: used_end,
+                severity: Severity::Error,
+                code: None,
             });
         }
 
@@ -822,6 +907,7 @@ impl<'a> ForgeScriptParser<'a> {
                 negated,
                 count: None,
                 meta,
+                depth: 0,
             });
         }
     }
@@ -846,26 +932,12 @@ fn compute_arg_counts(meta: &Function) -> (usize, usize) {
     (min, max)
 }
 
-fn find_matching_brace(code: &str, open: usize) -> Option<usize> {
-    let mut depth = 0;
-    for (i, c) in code.char_indices().skip_while(|&(i, _)| i < open) {
-        if c == '{' {
-            depth += 1;
-        } else if c == '}' {
-            depth -= 1;
-            if depth == 0 {
-                return Some(i);
-            }
-        }
-    }
-    None
-}
-
 fn parse_nested_args(
     input: &str,
     manager: &Arc<MetadataManager>,
     diags: &mut Vec<Diagnostic>,
     funcs: &mut Vec<ParsedFunction>,
+    tokens: &mut Vec<Token>,
     base: usize,
 ) -> Result<Vec<(SmallVec<[ParsedArg; 8]>, (usize, usize))>, nom::Err<()>> {
     let mut args = Vec::new();
@@ -924,7 +996,15 @@ fn parse_nested_args(
                 let off = base + arg_start + leading;
                 if !trimmed.is_empty() {
                     args.push((
-                        parse_single_arg(trimmed, manager, first_escaped, diags, funcs, off),
+                        parse_single_arg(
+                            trimmed,
+                            manager,
+                            first_escaped,
+                            diags,
+                            funcs,
+                            tokens,
+                            off,
+                        ),
                         (off, off + trimmed.len()),
                     ));
                 } else {
@@ -957,7 +1037,7 @@ fn parse_nested_args(
         let off = base + arg_start + leading;
         if !trimmed.is_empty() {
             args.push((
-                parse_single_arg(trimmed, manager, first_escaped, diags, funcs, off),
+                parse_single_arg(trimmed, manager, first_escaped, diags, funcs, tokens, off),
                 (off, off + trimmed.len()),
             ));
         } else {
@@ -1067,9 +1147,11 @@ fn parse_single_arg(
     force: bool,
     diags: &mut Vec<Diagnostic>,
     funcs: &mut Vec<ParsedFunction>,
+    tokens: &mut Vec<Token>,
     base: usize,
 ) -> SmallVec<[ParsedArg; 8]> {
-    if !force && input.starts_with('$') {
+    // Calls may follow plain text, as in `Hello $username`.
+    if !force && input.contains('$') {
         let mut res = ForgeScriptParser::new_internal((*mgr).clone(), input).parse_internal();
         for mut d in res.diagnostics {
             d.start += base;
@@ -1080,11 +1162,16 @@ fn parse_single_arg(
             f.offset_spans(base);
             funcs.push(f.clone());
         }
-        if let Some(f) = res.functions.first() {
-            let mut func = f.clone();
-            func.offset_spans(base);
+        tokens.extend(res.tokens.into_iter().map(|t| Token {
+            start: t.start + base,
+            end: t.end + base,
+            ..t
+        }));
+        if input.starts_with('$')
+            && let Some(func) = res.functions.first()
+        {
             return smallvec![ParsedArg::Function {
-                func: Box::new(func)
+                func: Box::new(func.clone())
             }];
         }
     }
@@ -1112,12 +1199,16 @@ fn validate_arg_count(
             message: format!("${name} expects >= {min} args, got {tot}"),
             start: span.0,
             end: span.1,
+            severity: Severity::Error,
+            code: None,
         });
     } else if !rest && tot > max {
         diags.push(Diagnostic {
             message: format!("${name} expects <= {max} args, got {tot}"),
             start: span.0,
             end: span.1,
+            severity: Severity::Error,
+            code: None,
         });
     }
 }
@@ -1172,6 +1263,8 @@ fn validate_arg_enums(
                     ),
                     start: span.0,
                     end: span.1,
+                    severity: Severity::Error,
+                    code: None,
                 });
             }
         }
@@ -0,0 +1,104 @@
+//! Work-done progress reporting for long-running server tasks.
+//!
+//! Wraps the `window/workDoneProgress/create` handshake and `$/progress`
+//! notifications behind a synchronous reporter, so that progress can be
+//! reported from plain callbacks while notifications still arrive in order.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::StreamExt;
+use futures::channel::mpsc::{UnboundedSender, unbounded};
+use tower_lsp::Client;
+use tower_lsp::lsp_types::notification::Progress;
+use tower_lsp::lsp_types::request::WorkDoneProgressCreate;
+#[allow(clippy::wildcard_imports)]
+use tower_lsp::lsp_types::*;
+
+static NEXT_TOKEN: AtomicU64 = AtomicU64::new(0);
+
+/// Handle to an active work-done progress; does nothing when the client lacks support.
+#[derive(Debug)]
+pub struct ProgressReporter {
+    tx: Option<UnboundedSender<WorkDoneProgress>>,
+}
+
+impl ProgressReporter {
+    /// A reporter that silently discards all updates.
+    pub fn disabled() -> Self {
+        Self { tx: None }
+    }
+
+    /// Creates a progress token on the client and sends the `begin` notification.
+    pub async fn begin(client: &Client, enabled: bool, title: &str) -> Self {
+        if !enabled {
+            return Self::disabled();
+        }
+
+        let id = NEXT_TOKEN.fetch_add(1, Ordering::Relaxed);
+        let token = NumberOrString::String(format!("forgelsp/{id}"));
+        if client
+            .send_request::<WorkDoneProgressCreate>(WorkDoneProgressCreateParams {
+                token: token.clone(),
+            })
+            .await
+            .is_err()
+        {
+            crate::utils::forge_log(
+                crate::utils::LogLevel::Debug,
+                &format!("Client rejected progress token for '{title}'"),
+            );
+            return Self::disabled();
+        }
+
+        // A single forwarding task keeps notifications in the order they were reported.
+        let (tx, mut rx) = unbounded::<WorkDoneProgress>();
+        let client = client.clone();
+        tokio::spawn(async move {
+            while let Some(value) = rx.next().await {
+                client
+                    .send_notification::<Progress>(ProgressParams {
+                        token: token.clone(),
+                        value: ProgressParamsValue::WorkDone(value),
+                    })
+                    .await;
+            }
+        });
+
+        let reporter = Self { tx: Some(tx) };
+        reporter.send(WorkDoneProgress::Begin(WorkDoneProgressBegin {
+            title: title.to_string(),
+            cancellable: Some(false),
+            message: None,
+            percentage: Some(0),
+        }));
+        reporter
+    }
+
+    /// Reports an intermediate message, optionally with a percentage.
+    pub fn report(&self, message: impl Into<String>, percentage: Option<u32>) {
+        self.send(WorkDoneProgress::Report(WorkDoneProgressReport {
+            cancellable: Some(false),
+            message: Some(message.into()),
+            percentage,
+        }));
+    }
+
+    /// Reports step `done` of `total`, deriving the percentage from the counts.
+    pub fn report_step(&self, done: usize, total: usize, message: impl Into<String>) {
+        let percentage = (done * 100).checked_div(total).unwrap_or(100) as u32;
+        self.report(message, Some(percentage.min(100)));
+    }
+
+    /// Finishes the progress with a final summary message.
+    pub fn end(self, message: impl Into<String>) {
+        self.send(WorkDoneProgress::End(WorkDoneProgressEnd {
+            message: Some(message.into()),
+        }));
+    }
+
+    fn send(&self, value: WorkDoneProgress) {
+        if let Some(tx) = &self.tx {
+            tx.unbounded_send(value).ok();
+        }
+    }
+}
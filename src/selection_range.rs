@@ -0,0 +1,129 @@
+use tower_lsp::jsonrpc::Result;
+use tower_lsp::lsp_types::*;
+
+use crate::parser::{ParseResult, code_blocks};
+use crate::server::ForgeScriptServer;
+use crate::utils::{offset_to_position, position_to_offset};
+
+/// Expands from the word under the cursor to its argument, the argument list, the call,
+/// each enclosing call and finally the code block.
+pub async fn handle_selection_range(
+    server: &ForgeScriptServer,
+    params: SelectionRangeParams,
+) -> Result<Option<Vec<SelectionRange>>> {
+    let uri = params.text_document.uri;
+    let Some(text) = server
+        .documents
+        .read()
+        .expect("Server: lock poisoned")
+        .get(&uri)
+        .cloned()
+    else {
+        return Ok(None);
+    };
+    let Some(parsed) = server
+        .parsed_cache
+        .read()
+        .expect("Server: lock poisoned")
+        .get(&uri)
+        .cloned()
+    else {
+        return Ok(None);
+    };
+
+    Ok(Some(
+        params
+            .positions
+            .into_iter()
+            .map(|position| {
+                let spans = position_to_offset(&text, position)
+                    .map(|offset| selection_spans(&text, &parsed, offset))
+                    .unwrap_or_default();
+                // Build the chain outwards-in so each range points at its parent.
+                let mut selection: Option<SelectionRange> = None;
+                for (start, end) in spans.into_iter().rev() {
+                    selection = Some(SelectionRange {
+                        range: Range::new(
+                            offset_to_position(&text, start),
+                            offset_to_position(&text, end),
+                        ),
+                        parent: selection.map(Box::new),
+                    });
+                }
+                selection.unwrap_or(SelectionRange {
+                    range: Range::new(position, position),
+                    parent: None,
+                })
+            })
+            .collect(),
+    ))
+}
+
+/// Returns the selectable spans around `offset`, innermost first, each containing the last.
+fn selection_spans(text: &str, parsed: &ParseResult, offset: usize) -> Vec<(usize, usize)> {
+    let mut candidates = Vec::new();
+
+    let is_word = |c: char| c.is_alphanumeric() || c == '_' || c == '$';
+    let word_start = text[..offset]
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| is_word(c))
+        .last()
+        .map_or(offset, |(i, _)| i);
+    let word_end = text[offset..]
+        .char_indices()
+        .find(|&(_, c)| !is_word(c))
+        .map_or(text.len(), |(i, _)| offset + i);
+    if word_start < word_end {
+        candidates.push((word_start, word_end));
+    }
+
+    let mut enclosing: Vec<_> = parsed
+        .functions
+        .iter()
+        .filter(|f| f.span.0 <= offset && offset <= f.span.1)
+        .collect();
+    enclosing.sort_by_key(|f| f.span.1 - f.span.0);
+    for func in enclosing {
+        if let Some(args) = &func.args {
+            if let Some((_, span)) = args
+                .iter()
+                .find(|(_, (start, end))| *start <= offset && offset <= *end)
+            {
+                candidates.push(*span);
+            }
+            // The argument list is everything between the call's own brackets.
+            let open = parsed
+                .tokens
+                .iter()
+                .find(|t| t.start == func.span.0)
+                .map(|t| t.end)
+                .filter(|&open| text.as_bytes().get(open) == Some(&b'['));
+            if let Some(open) = open
+                && open < offset
+                && offset < func.span.1
+            {
+                candidates.push((open + 1, func.span.1 - 1));
+            }
+        }
+        candidates.push(func.span);
+    }
+
+    if let Some(block) = code_blocks(text)
+        .into_iter()
+        .find(|&(start, end)| start <= offset && offset <= end)
+    {
+        candidates.push(block);
+    }
+
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+    for span in candidates {
+        let grows = spans.last().is_none_or(|&(start, end)| {
+            span.0 <= start && end <= span.1 && (span.0, span.1) != (start, end)
+        });
+        if grows {
+            spans.push(span);
+        }
+    }
+    spans
+}
@@ -1,58 +1,217 @@
-//! Analyzes ForgeScript source code to extract semantic tokens for highlighting.
+//! Derives semantic tokens for highlighting from ForgeScript parse results.
 //!
-//! Validates function calls against available metadata to distinguish valid
-//! ForgeScript identifiers from text.
+//! Highlighting reads the same `ParseResult` the diagnostics come from, so
+//! both always agree on escapes, modifiers and function name matching.
 
-use crate::metadata::MetadataManager;
+use crate::metadata::{Arg, MetadataManager};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::parser::ForgeScriptParser;
+use crate::parser::{ParseResult, ParsedArg, ParsedFunction, Token, TokenKind};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::server::{CustomNotification, ForgeHighlightsParams, ForgeScriptServer, HighlightRange};
-use crate::utils::{find_matching_bracket_raw, is_escaped, offset_to_position};
+use crate::utils::{find_matching_bracket_raw, offset_to_position};
 use lsp_types::*;
-use regex::Regex;
-use std::sync::{Arc, LazyLock};
+use std::collections::HashMap;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::atomic::{AtomicU64, Ordering};
 #[cfg(not(target_arch = "wasm32"))]
 use tower_lsp::jsonrpc::Result;
 
-/// Identifies ForgeScript code blocks in host configuration files.
-static CODE_BLOCK_RE: LazyLock<Regex> = LazyLock::new(|| {
-    Regex::new(r"(?s)code:\s*`((?:[^`\\]|\\.)*)`").expect("Semantic: regex failure")
-});
+/// Semantic token modifiers, in the order their bits are assigned below.
+pub const TOKEN_MODIFIERS: [SemanticTokenModifier; 11] = [
+    SemanticTokenModifier::DEPRECATED,
+    SemanticTokenModifier::new("silent"),
+    SemanticTokenModifier::new("negated"),
+    SemanticTokenModifier::new("custom"),
+    SemanticTokenModifier::new("unknown"),
+    SemanticTokenModifier::new("depth0"),
+    SemanticTokenModifier::new("depth1"),
+    SemanticTokenModifier::new("depth2"),
+    SemanticTokenModifier::new("depth3"),
+    SemanticTokenModifier::new("depth4"),
+    SemanticTokenModifier::new("depth5"),
+];
+
+/// The function is marked deprecated in its metadata.
+const MODIFIER_DEPRECATED: u32 = 1 << 0;
+/// The call is silenced with `$!`.
+const MODIFIER_SILENT: u32 = 1 << 1;
+/// The call is negated with `$#`.
+const MODIFIER_NEGATED: u32 = 1 << 2;
+/// The function comes from the user's custom functions.
+const MODIFIER_CUSTOM: u32 = 1 << 3;
+/// No function with this name exists.
+const MODIFIER_UNKNOWN: u32 = 1 << 4;
+/// Names, brackets and separators of calls nested at depth `n` get `MODIFIER_DEPTH0 << (n %
+/// DEPTH_LEVELS)`, so any client theme can color nesting without custom notifications.
+const MODIFIER_DEPTH0: u32 = 1 << 5;
+const DEPTH_LEVELS: usize = 6;
+
+/// Produces semantic tokens from an existing parse of `source`.
+pub fn semantic_tokens_from_parse(
+    source: &str,
+    parsed: &ParseResult,
+    use_function_colors: bool,
+    manager: &MetadataManager,
+) -> Vec<SemanticToken> {
+    let tokens = collect_tokens(
+        source,
+        parsed,
+        use_function_colors,
+        manager,
+        (0, source.len()),
+    );
+    // Convert absolute token offsets to relative offsets for the LSP payload.
+    to_relative_tokens(&tokens, source)
+}
 
-/// Processes document content to produce an LSP-compatible set of semantic tokens.
-pub fn extract_semantic_tokens_with_colors(
+/// Produces the semantic tokens overlapping the byte range `start..end` of `source`.
+pub fn semantic_tokens_in_range(
     source: &str,
+    parsed: &ParseResult,
     use_function_colors: bool,
-    manager: &Arc<MetadataManager>,
+    manager: &MetadataManager,
+    (start, end): (usize, usize),
 ) -> Vec<SemanticToken> {
-    let start = crate::utils::Instant::now();
-    let mut tokens = Vec::new();
+    let tokens = collect_tokens(source, parsed, use_function_colors, manager, (start, end));
+    to_relative_tokens(&tokens, source)
+}
 
-    // Iterate through all "code:" blocks found in the source.
-    for cap in CODE_BLOCK_RE.captures_iter(source) {
-        if let Some(match_group) = cap.get(1) {
-            let content_start = match_group.start();
-            let code = match_group.as_str();
+/// Computes the sorted `(start, end, type, modifiers)` tokens overlapping the byte range
+/// `start..end` of a parsed document.
+///
+/// Tokens and functions are sorted by start, so only those inside the range and the calls
+/// enclosing it are visited.
+fn collect_tokens(
+    source: &str,
+    parsed: &ParseResult,
+    use_function_colors: bool,
+    manager: &MetadataManager,
+    (start, end): (usize, usize),
+) -> Vec<(usize, usize, u32, u32)> {
+    let timer = crate::utils::Instant::now();
+    // A token starting before the range may still reach into it.
+    let first_token = parsed
+        .tokens
+        .partition_point(|t| t.start < start)
+        .saturating_sub(1);
+    let last_token = parsed.tokens.partition_point(|t| t.start < end);
+    let window = &parsed.tokens[first_token..last_token];
+    let from = window.first().map_or(start, |t| t.start.min(start));
+
+    let first_function = parsed.functions.partition_point(|f| f.span.0 < from);
+    let last_function = parsed.functions.partition_point(|f| f.span.0 < end);
+    let enclosing = enclosing_functions(&parsed.functions[..first_function], from);
+    let functions: Vec<&ParsedFunction> = enclosing
+        .iter()
+        .copied()
+        .chain(&parsed.functions[first_function..last_function])
+        .collect();
+
+    let mut comments = comment_spans(source, window);
+    comments.extend(
+        enclosing
+            .iter()
+            .filter(|f| f.name.eq_ignore_ascii_case("c"))
+            .map(|f| f.span),
+    );
+    comments.sort_unstable();
+    comments.dedup();
+    let mut in_comment = CommentCursor::new(&comments);
+    let mut depth = DepthCursor::new(
+        &parsed.functions[first_function..],
+        enclosing.iter().map(|f| f.span.1).collect(),
+    );
+    let mut functions_by_start = HashMap::new();
+    for &func in &functions {
+        functions_by_start.entry(func.span.0).or_insert(func);
+    }
 
-            let found_tokens =
-                extract_tokens_from_code(code, content_start, use_function_colors, manager);
-            tokens.extend(found_tokens);
+    let mut tokens = Vec::new();
+    // Separators and closing brackets of the enclosing calls may fall inside the range.
+    for func in &enclosing {
+        if let Ok(i) = parsed
+            .tokens
+            .binary_search_by_key(&func.span.0, |t| t.start)
+        {
+            push_call_punctuation(
+                source,
+                &parsed.tokens[i],
+                Some(func),
+                func.depth,
+                &mut tokens,
+            );
         }
     }
+    for token in window {
+        if in_comment.contains(token.start) {
+            continue;
+        }
+        match token.kind {
+            TokenKind::FunctionName => {
+                // Alternates between 0 and 3 by the call's position in the document.
+                let token_type = if use_function_colors {
+                    (parsed.functions.partition_point(|f| f.span.0 < token.start) % 2) as u32 * 3
+                } else {
+                    0
+                };
+                let func = functions_by_start.get(&token.start).copied();
+                let depth = depth.at(token.start);
+                push_function_name(
+                    &mut tokens,
+                    token,
+                    token_type,
+                    function_modifiers(token, func, depth),
+                );
+                push_call_punctuation(source, token, func, depth, &mut tokens);
+            }
+            TokenKind::Unknown => {
+                let func = functions_by_start.get(&token.start).copied();
+                let depth = depth.at(token.start);
+                push_function_name(
+                    &mut tokens,
+                    token,
+                    0,
+                    function_modifiers(token, None, depth) | MODIFIER_UNKNOWN,
+                );
+                push_call_punctuation(source, token, func, depth, &mut tokens);
+            }
+            TokenKind::Escaped => {
+                let esc_end = token.end - 1;
+                let name_end = esc_end - token.text.len();
+                tokens.push((token.start, name_end, 0, 0));
+                if name_end < esc_end {
+                    tokens.push((name_end, esc_end, 4, 0));
+                }
+                tokens.push((esc_end, token.end, 0, 0));
+            }
+            TokenKind::JavaScript => {
+                js_expression_tokens(source, token.start, token.end, &mut tokens)
+            }
+            TokenKind::Text => {}
+        }
+    }
+    tokens.extend(argument_tokens(source, &functions, manager));
+    tokens.sort_by_key(|(s, ..)| *s);
+    let mut in_comment = CommentCursor::new(&comments);
+    tokens.retain(|&(s, ..)| !in_comment.contains(s));
+    tokens.extend(comments.iter().map(|&(s, e)| (s, e, 5, 0)));
+    tokens.retain(|&(s, e, ..)| s < end && e > start);
+    tokens.sort_by_key(|(s, ..)| *s);
 
     crate::utils::forge_log(
         crate::utils::LogLevel::Debug,
-        &format!("Extracted semantic tokens in {}", start.elapsed_display()),
+        &format!("Extracted semantic tokens in {}", timer.elapsed_display()),
     );
-    // Convert absolute token offsets to relative offsets for the LSP payload.
-    to_relative_tokens(&tokens, source)
+    tokens
 }
 
-/// Identifies highlighting ranges for VS Code-specific decorations.
-pub fn extract_highlight_ranges(
+/// Identifies highlighting ranges from an existing parse of `source`.
+pub fn highlight_ranges_from_parse(
     source: &str,
+    parsed: &ParseResult,
     function_colors: &[String],
     consistent_colors: bool,
-    manager: &Arc<MetadataManager>,
 ) -> Vec<(usize, usize, String)> {
     let mut highlights = Vec::new();
     if function_colors.is_empty() {
@@ -60,272 +219,351 @@ pub fn extract_highlight_ranges(
     }
 
     let mut color_index = 0usize;
-    let mut function_to_color = std::collections::HashMap::new();
-
-    for cap in CODE_BLOCK_RE.captures_iter(source) {
-        if let Some(match_group) = cap.get(1) {
-            let content_start = match_group.start();
-            let code = match_group.as_str();
-            let char_positions: Vec<(usize, char)> = code.char_indices().collect();
-            let mut idx = 0;
-
-            while idx < char_positions.len() {
-                let (i, c) = char_positions[idx];
-
-                if c == '$' && !is_escaped(code, i) {
-                    if let Some((_, next_idx)) = try_extract_comment(code, idx, &char_positions) {
-                        idx = next_idx;
-                        continue;
-                    }
-
-                    if let Some((_, _, next_idx)) = try_extract_escape(code, idx, &char_positions) {
-                        idx = next_idx;
-                        continue;
-                    }
-
-                    if let Some((best_match_len, best_match_char_count)) =
-                        try_extract_metadata_function(code, idx, &char_positions, manager)
-                    {
-                        let raw_func = &code[i..i + best_match_len];
-                        let base_name = if consistent_colors {
-                            let name_start = try_find_name_start(raw_func);
-                            format!("${}", &raw_func[name_start..])
-                        } else {
-                            raw_func.to_string()
-                        };
-
-                        let color = if consistent_colors {
-                            function_to_color
-                                .entry(base_name)
-                                .or_insert_with(|| {
-                                    let c = function_colors[color_index % function_colors.len()]
-                                        .clone();
-                                    color_index += 1;
-                                    c
-                                })
-                                .clone()
-                        } else {
-                            let c = function_colors[color_index % function_colors.len()].clone();
-                            color_index += 1;
-                            c
-                        };
-
-                        highlights.push((
-                            i + content_start,
-                            i + best_match_len + content_start,
-                            color,
-                        ));
-                        idx += best_match_char_count;
-                        continue;
-                    }
-                }
-                idx += 1;
-            }
+    let mut function_to_color = HashMap::new();
+    let comments = comment_spans(source, &parsed.tokens);
+    let mut in_comment = CommentCursor::new(&comments);
+
+    for token in &parsed.tokens {
+        if token.kind != TokenKind::FunctionName || in_comment.contains(token.start) {
+            continue;
         }
+        let color = if consistent_colors {
+            let name_start = try_find_name_start(&token.text);
+            let base_name = format!("${}", &token.text[name_start..]);
+            function_to_color
+                .entry(base_name)
+                .or_insert_with(|| {
+                    let c = function_colors[color_index % function_colors.len()].clone();
+                    color_index += 1;
+                    c
+                })
+                .clone()
+        } else {
+            let c = function_colors[color_index % function_colors.len()].clone();
+            color_index += 1;
+            c
+        };
+        highlights.push((token.start, token.end, color));
     }
     highlights
 }
 
-/// Tokenizes the content of a ForgeScript code block.
-fn extract_tokens_from_code(
-    code: &str,
-    code_start: usize,
-    use_function_colors: bool,
-    manager: &Arc<MetadataManager>,
-) -> Vec<(usize, usize, u32)> {
-    let mut found = Vec::new();
-    let mut function_color_index = 0u32;
-    let char_positions: Vec<(usize, char)> = code.char_indices().collect();
-    let mut idx = 0;
-
-    while idx < char_positions.len() {
-        let (i, c) = char_positions[idx];
-
-        if c == '$' && !is_escaped(code, i) {
-            if let Some((end_idx, next_idx)) = try_extract_comment(code, idx, &char_positions) {
-                found.push((i + code_start, end_idx + 1 + code_start, 5));
-                idx = next_idx;
-                continue;
-            }
-
-            if let Some((name_end, esc_end, next_idx)) =
-                try_extract_escape(code, idx, &char_positions)
-            {
-                found.push((i + code_start, name_end + code_start, 0));
-                if name_end < esc_end {
-                    found.push((name_end + code_start, esc_end + code_start, 4));
-                }
-                found.push((esc_end + code_start, esc_end + 1 + code_start, 0));
-                idx = next_idx;
-                continue;
-            }
+/// Pushes a function name token, splitting out its `@[...]` count modifiers.
+fn push_function_name(
+    found: &mut Vec<(usize, usize, u32, u32)>,
+    token: &Token,
+    token_type: u32,
+    modifiers: u32,
+) {
+    let mut from = token.start;
+    for (open, close) in count_modifier_spans(&token.text) {
+        let (open, close) = (token.start + open, token.start + close);
+        if from < open {
+            found.push((from, open, token_type, modifiers));
+        }
+        found.push((open, open + 2, 6, 0));
+        let inner = &token.text[open + 2 - token.start..close - token.start];
+        let count = inner.trim();
+        if is_number(count) {
+            let count_start = open + 2 + inner.len() - inner.trim_start().len();
+            found.push((count_start, count_start + count.len(), 2, 0));
+        }
+        found.push((close, close + 1, 6, 0));
+        from = close + 1;
+    }
+    if from < token.end {
+        found.push((from, token.end, token_type, modifiers));
+    }
+}
 
-            if let Some((best_match_len, best_match_char_count)) =
-                try_extract_metadata_function(code, idx, &char_positions, manager)
-            {
-                let token_type = if use_function_colors {
-                    let color = (function_color_index % 2) * 3; // Alternates between 0 and 3
-                    function_color_index += 1;
-                    color
-                } else {
-                    0
+/// Returns the `@` and `]` offsets of each `@[...]` modifier in a raw function name.
+fn count_modifier_spans(raw_func: &str) -> Vec<(usize, usize)> {
+    let bytes = raw_func.as_bytes();
+    let mut spans = Vec::new();
+    let mut i = 1;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'!' | b'#' => i += 1,
+            b'@' if bytes.get(i + 1) == Some(&b'[') => {
+                let Some(close) = find_matching_bracket_raw(bytes, i + 1) else {
+                    break;
                 };
-                found.push((i + code_start, i + best_match_len + code_start, token_type));
-                idx += best_match_char_count;
-                continue;
+                spans.push((i, close));
+                i = close + 1;
             }
+            _ => break,
         }
+    }
+    spans
+}
 
-        if let Some(slice) = code.get(i..) {
-            if slice.starts_with("true") {
-                found.push((i + code_start, i + 4 + code_start, 1));
-                idx += 4;
-                continue;
-            }
-            if slice.starts_with("false") {
-                found.push((i + code_start, i + 5 + code_start, 1));
-                idx += 5;
-                continue;
-            }
+/// Pushes the brackets and argument separators of the call named by `token`, tagged
+/// with the call's depth.
+fn push_call_punctuation(
+    source: &str,
+    token: &Token,
+    func: Option<&ParsedFunction>,
+    depth: usize,
+    found: &mut Vec<(usize, usize, u32, u32)>,
+) {
+    let Some(func) = func else {
+        return;
+    };
+    let close = func.span.1 - 1;
+    if close <= token.end
+        || source.as_bytes().get(token.end) != Some(&b'[')
+        || source.as_bytes().get(close) != Some(&b']')
+    {
+        return;
+    }
+    let modifiers = depth_modifier(depth);
+    found.push((token.end, token.end + 1, 1, modifiers));
+    found.extend(
+        func.separator_offsets(source)
+            .into_iter()
+            .map(|sep| (sep, sep + 1, 1, modifiers)),
+    );
+    found.push((close, close + 1, 1, modifiers));
+}
+
+/// Finds the calls among `functions`, all starting before `pos`, whose brackets enclose
+/// `pos`, outermost first.
+///
+/// The walk goes backwards and stops at the first top-level call, since no earlier call
+/// can reach past it.
+fn enclosing_functions(functions: &[ParsedFunction], pos: usize) -> Vec<&ParsedFunction> {
+    let mut enclosing = Vec::new();
+    for func in functions.iter().rev() {
+        if pos < func.span.1 {
+            enclosing.push(func);
         }
+        if func.depth == 0 {
+            break;
+        }
+    }
+    enclosing.reverse();
+    enclosing
+}
+
+/// Counts the calls enclosing each queried offset with a single stack walk over the
+/// functions, which the parser sorts by span, as long as the offsets never decrease.
+struct DepthCursor<'a> {
+    functions: &'a [ParsedFunction],
+    next: usize,
+    /// Ends of the calls enclosing the last queried offset, innermost last.
+    open: Vec<usize>,
+}
 
-        if c == ';' && !is_escaped(code, i) {
-            found.push((i + code_start, i + 1 + code_start, 1));
+impl<'a> DepthCursor<'a> {
+    /// Starts the walk at `functions`, inside the calls ending at `open`.
+    fn new(functions: &'a [ParsedFunction], open: Vec<usize>) -> Self {
+        Self {
+            functions,
+            next: 0,
+            open,
         }
+    }
 
-        idx += 1;
+    fn at(&mut self, pos: usize) -> usize {
+        while let Some(func) = self.functions.get(self.next)
+            && func.span.0 < pos
+        {
+            self.close_before(func.span.0);
+            self.open.push(func.span.1);
+            self.next += 1;
+        }
+        self.close_before(pos);
+        self.open.len()
     }
 
-    found.sort_by_key(|(s, _, _)| *s);
-    found
+    fn close_before(&mut self, pos: usize) {
+        while self.open.last().is_some_and(|&end| end <= pos) {
+            self.open.pop();
+        }
+    }
 }
 
-fn try_extract_comment(
-    code: &str,
-    idx: usize,
-    char_positions: &[(usize, char)],
-) -> Option<(usize, usize)> {
-    if idx + 2 < char_positions.len()
-        && char_positions[idx + 1].1 == 'c'
-        && char_positions[idx + 2].1 == '['
-    {
-        if let Some(end_idx) = find_matching_bracket_raw(code.as_bytes(), char_positions[idx + 2].0)
-        {
-            let mut next_idx = idx;
-            while next_idx < char_positions.len() && char_positions[next_idx].0 <= end_idx {
-                next_idx += 1;
-            }
-            return Some((end_idx, next_idx));
+fn depth_modifier(depth: usize) -> u32 {
+    MODIFIER_DEPTH0 << (depth % DEPTH_LEVELS)
+}
+
+/// Computes the modifier bits of a function name token.
+fn function_modifiers(token: &Token, func: Option<&ParsedFunction>, depth: usize) -> u32 {
+    let prefix = &token.text[..try_find_name_start(&token.text)];
+    let mut modifiers = depth_modifier(depth);
+    if prefix.contains('!') {
+        modifiers |= MODIFIER_SILENT;
+    }
+    if prefix.contains('#') {
+        modifiers |= MODIFIER_NEGATED;
+    }
+    if let Some(func) = func {
+        if func.meta.deprecated == Some(true) {
+            modifiers |= MODIFIER_DEPRECATED;
+        }
+        if crate::metadata::is_custom(&func.meta) {
+            modifiers |= MODIFIER_CUSTOM;
         }
     }
-    None
+    modifiers
 }
 
-fn try_extract_escape(
-    code: &str,
-    idx: usize,
-    char_positions: &[(usize, char)],
-) -> Option<(usize, usize, usize)> {
-    let bytes = code.as_bytes();
-    let i = char_positions[idx].0;
-    if let Some(esc_end) = crate::utils::find_escape_function_end(code, i) {
-        let name_end = i + if bytes.get(i + 1..).and_then(|b| b.get(..4)) == Some(b"esc[") {
-            4
-        } else {
-            11
-        };
-        let mut next_idx = idx;
-        while next_idx < char_positions.len() && char_positions[next_idx].0 <= esc_end {
-            next_idx += 1;
+/// Finds every `$c[...]` comment, including `$c[fs@ignore-error]` directives.
+fn comment_spans(source: &str, tokens: &[Token]) -> Vec<(usize, usize)> {
+    tokens
+        .iter()
+        .filter_map(|token| match token.kind {
+            TokenKind::Text if token.text.starts_with("$c[") => Some((token.start, token.end)),
+            TokenKind::FunctionName | TokenKind::Unknown => {
+                let name = &token.text[try_find_name_start(&token.text)..];
+                if !name.eq_ignore_ascii_case("c")
+                    || source.as_bytes().get(token.end) != Some(&b'[')
+                {
+                    return None;
+                }
+                find_matching_bracket_raw(source.as_bytes(), token.end)
+                    .map(|close| (token.start, close + 1))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Tells whether offsets fall inside comments, walking the sorted comment spans once
+/// as long as the queried offsets never decrease.
+struct CommentCursor<'a> {
+    spans: &'a [(usize, usize)],
+    next: usize,
+}
+
+impl<'a> CommentCursor<'a> {
+    fn new(spans: &'a [(usize, usize)]) -> Self {
+        Self { spans, next: 0 }
+    }
+
+    fn contains(&mut self, pos: usize) -> bool {
+        while self
+            .spans
+            .get(self.next)
+            .is_some_and(|&(_, end)| end <= pos)
+        {
+            self.next += 1;
         }
-        return Some((name_end, esc_end, next_idx));
+        self.spans
+            .get(self.next)
+            .is_some_and(|&(start, _)| start <= pos)
     }
-    None
 }
 
-fn try_extract_metadata_function(
-    code: &str,
-    idx: usize,
-    char_positions: &[(usize, char)],
+/// Finds literal arguments and condition operators.
+fn argument_tokens(
+    source: &str,
+    functions: &[&ParsedFunction],
     manager: &MetadataManager,
-) -> Option<(usize, usize)> {
-    let i = char_positions[idx].0;
-    let mut j = idx + 1;
-
-    // Skip ForgeScript modifiers
-    while j < char_positions.len() {
-        let (_, c) = char_positions[j];
-        if c == '!' || c == '#' {
-            j += 1;
-        } else if c == '@' && j + 1 < char_positions.len() && char_positions[j + 1].1 == '[' {
-            if let Some(close_idx) =
-                find_matching_bracket_raw(code.as_bytes(), char_positions[j + 1].0)
+) -> Vec<(usize, usize, u32, u32)> {
+    let mut found = Vec::new();
+    for func in functions {
+        let Some(args) = &func.args else {
+            continue;
+        };
+        let meta_args = func.meta.args.as_deref().unwrap_or_default();
+        for (i, (parts, (start, end))) in args.iter().enumerate() {
+            let meta = meta_args
+                .get(i)
+                .or_else(|| meta_args.last().filter(|a| a.rest));
+            if let [ParsedArg::Literal { text }] = parts.as_slice()
+                && let Some(token_type) = literal_token_type(text, meta, manager)
             {
-                while j < char_positions.len() && char_positions[j].0 <= close_idx {
-                    j += 1;
-                }
-            } else {
-                break;
+                found.push((*start, *end, token_type, 0));
             }
-        } else {
-            break;
         }
     }
 
-    if j >= char_positions.len() {
-        return None;
-    }
+    found.extend(
+        crate::condition::condition_args(functions.iter().copied())
+            .flat_map(|(_, (start, end))| {
+                let text = source.get(start..end).unwrap_or_default();
+                crate::condition::parse_condition(text, start)
+                    .map(|condition| condition.operator_spans())
+                    .unwrap_or_else(|_| crate::condition::operator_spans(text, start))
+            })
+            .map(|(start, end)| (start, end, 6, 0)),
+    );
+    found
+}
 
-    let name_start_byte = char_positions[j].0;
-    let mut name_end_char = j;
-    while name_end_char < char_positions.len()
-        && (char_positions[name_end_char].1.is_alphanumeric()
-            || char_positions[name_end_char].1 == '_')
-    {
-        name_end_char += 1;
+/// Classifies a literal argument by the parameter it is passed to.
+fn literal_token_type(text: &str, arg: Option<&Arg>, manager: &MetadataManager) -> Option<u32> {
+    if text == "true" || text == "false" {
+        return Some(1);
     }
-
-    let has_bracket =
-        name_end_char < char_positions.len() && char_positions[name_end_char].1 == '[';
-    let check_fn = |end_char_idx: usize| -> bool {
-        let end_byte = if end_char_idx < char_positions.len() {
-            char_positions[end_char_idx].0
-        } else {
-            code.len()
-        };
-        code.get(name_start_byte..end_byte)
-            .map(|name| manager.get_exact(&format!("${name}")).is_some())
-            .unwrap_or(false)
+    let arg = arg?;
+    let numeric = match &arg.arg_type {
+        serde_json::Value::String(t) => matches!(t.as_str(), "Number" | "BigInt"),
+        serde_json::Value::Array(types) => types
+            .iter()
+            .any(|t| matches!(t.as_str(), Some("Number" | "BigInt"))),
+        _ => false,
     };
+    if numeric && is_number(text) {
+        return Some(2);
+    }
+    let valid_enum = match &arg.enum_name {
+        Some(name) => manager
+            .enums
+            .read()
+            .expect("MetadataManager: lock poisoned")
+            .get(name)
+            .is_some_and(|values| values.iter().any(|v| v == text)),
+        None => arg
+            .arg_enum
+            .as_ref()
+            .is_some_and(|values| values.iter().any(|v| v == text)),
+    };
+    valid_enum.then_some(8)
+}
 
-    if has_bracket {
-        if check_fn(name_end_char) {
-            return Some((char_positions[name_end_char].0 - i, name_end_char - idx));
-        }
-    } else {
-        let mut check_idx = name_end_char;
-        while check_idx > j {
-            if check_fn(check_idx) {
-                let end_byte = if check_idx < char_positions.len() {
-                    char_positions[check_idx].0
-                } else {
-                    code.len()
-                };
-                return Some((end_byte - i, check_idx - idx));
-            }
-            check_idx -= 1;
-        }
+/// Whether `text` is a plain numeric literal such as `42`, `-1` or `0.5`.
+fn is_number(text: &str) -> bool {
+    text.bytes().any(|b| b.is_ascii_digit()) && text.parse::<f64>().is_ok()
+}
+
+/// Tokenizes the `${ ... }` JavaScript expression spanning `start..end`, including its delimiters.
+fn js_expression_tokens(
+    source: &str,
+    start: usize,
+    end: usize,
+    found: &mut Vec<(usize, usize, u32, u32)>,
+) {
+    use crate::embedded_js::JsTokenKind;
+
+    let close = end - 1;
+    found.push((start, start + 2, 1, 0));
+    let tokens = crate::embedded_js::analyze_expression(source, start + 1)
+        .map(|(_, analysis)| analysis.tokens)
+        .unwrap_or_default();
+    for token in tokens {
+        let token_type = match token.kind {
+            JsTokenKind::Keyword => 1,
+            JsTokenKind::Number => 2,
+            JsTokenKind::String | JsTokenKind::Regex => 4,
+            JsTokenKind::Comment => 5,
+            JsTokenKind::Operator => 6,
+            JsTokenKind::Identifier => 7,
+            JsTokenKind::Punctuation => continue,
+        };
+        found.push((token.start, token.end, token_type, 0));
     }
-    None
+    found.push((close, end, 1, 0));
 }
 
-/// Computes relative offsets for semantic tokens.
-fn to_relative_tokens(found: &[(usize, usize, u32)], source: &str) -> Vec<SemanticToken> {
+/// Computes relative offsets for `(start, end, type, modifiers)` semantic tokens.
+fn to_relative_tokens(found: &[(usize, usize, u32, u32)], source: &str) -> Vec<SemanticToken> {
     let mut tokens = Vec::new();
     let mut last_line = 0u32;
     let mut last_col = 0u32;
 
-    for &(start, end, token_type) in found {
+    for &(start, end, token_type, token_modifiers_bitset) in found {
         let start_pos = offset_to_position(source, start);
         let end_pos = offset_to_position(source, end);
 
@@ -341,24 +579,25 @@ fn to_relative_tokens(found: &[(usize, usize, u32)], source: &str) -> Vec<Semant
                 delta_start,
                 length: (end_pos.character - start_pos.character).max(1),
                 token_type,
-                token_modifiers_bitset: 0,
+                token_modifiers_bitset,
             });
             last_line = start_pos.line;
             last_col = start_pos.character;
         } else {
             let lines: Vec<&str> = source.lines().collect();
-            for line_idx in (start_pos.line as u32)..=(end_pos.line as u32) {
+            for line_idx in start_pos.line..=end_pos.line {
                 let delta_line: u32 = line_idx.saturating_sub(last_line);
                 let line_text = lines.get(line_idx as usize).unwrap_or(&"");
                 let (start_char, length) = if line_idx == start_pos.line {
                     (
                         start_pos.character,
-                        (line_text.chars().count() as u32).saturating_sub(start_pos.character),
+                        (line_text.encode_utf16().count() as u32)
+                            .saturating_sub(start_pos.character),
                     )
                 } else if line_idx == end_pos.line {
                     (0, end_pos.character)
                 } else {
-                    (0, line_text.chars().count() as u32)
+                    (0, line_text.encode_utf16().count() as u32)
                 };
 
                 if length > 0 {
@@ -372,7 +611,7 @@ fn to_relative_tokens(found: &[(usize, usize, u32)], source: &str) -> Vec<Semant
                         delta_start,
                         length,
                         token_type,
-                        token_modifiers_bitset: 0,
+                        token_modifiers_bitset,
                     });
                     last_line = line_idx;
                     last_col = start_char;
@@ -413,59 +652,157 @@ fn try_find_name_start(raw_func: &str) -> usize {
     }
 }
 
+/// Returns the parse cached by `process_text`, parsing `text` only when none exists yet.
 #[cfg(not(target_arch = "wasm32"))]
-pub async fn handle_semantic_tokens_full(
+fn cached_parse(server: &ForgeScriptServer, uri: &Url, text: &str) -> ParseResult {
+    let cached = server
+        .parsed_cache
+        .read()
+        .expect("Server: lock poisoned")
+        .get(uri)
+        .cloned();
+    cached.unwrap_or_else(|| ForgeScriptParser::new(server.manager_for(uri), text).parse())
+}
+
+/// Computes the semantic tokens of an open document, optionally limited to a range.
+#[cfg(not(target_arch = "wasm32"))]
+fn document_tokens(
     server: &ForgeScriptServer,
-    params: SemanticTokensParams,
-) -> Result<Option<SemanticTokensResult>> {
+    uri: &Url,
+    range: Option<Range>,
+) -> Result<Vec<SemanticToken>> {
     let text = server
         .documents
         .read()
         .expect("Server: lock poisoned")
-        .get(&params.text_document.uri)
+        .get(uri)
         .cloned()
         .ok_or(tower_lsp::jsonrpc::Error::invalid_params(
             "Document not found",
         ))?;
-    let use_colors = *server
-        .multiple_function_colors
-        .read()
-        .expect("Server: lock poisoned");
-    let mgr = server
-        .manager
+    let use_colors = server.color_settings_for(uri).multiple;
+    let parsed = cached_parse(server, uri, &text);
+    let mgr = server.manager_for(uri);
+    Ok(match range {
+        Some(range) => {
+            let offset =
+                |p: Position| crate::utils::position_to_offset(&text, p).unwrap_or(text.len());
+            let span = (offset(range.start), offset(range.end));
+            semantic_tokens_in_range(&text, &parsed, use_colors, &mgr, span)
+        }
+        None => semantic_tokens_from_parse(&text, &parsed, use_colors, &mgr),
+    })
+}
+
+/// Stores a full token set under a fresh result ID for later delta requests.
+#[cfg(not(target_arch = "wasm32"))]
+fn remember_tokens(
+    server: &ForgeScriptServer,
+    uri: &Url,
+    data: Vec<SemanticToken>,
+) -> SemanticTokens {
+    static NEXT_RESULT_ID: AtomicU64 = AtomicU64::new(1);
+    let tokens = SemanticTokens {
+        result_id: Some(NEXT_RESULT_ID.fetch_add(1, Ordering::Relaxed).to_string()),
+        data,
+    };
+    server
+        .semantic_tokens
+        .write()
+        .expect("Server: lock poisoned")
+        .insert(uri.clone(), (server.manager_for(uri), tokens.clone()));
+    tokens
+}
+
+/// Describes how to turn `old` into `new` as a single edit of the token array.
+#[cfg(not(target_arch = "wasm32"))]
+fn token_edits(old: &[SemanticToken], new: &[SemanticToken]) -> Vec<SemanticTokensEdit> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if prefix == old.len() && prefix == new.len() {
+        return Vec::new();
+    }
+    // Edit offsets count integers, and every token is five of them.
+    vec![SemanticTokensEdit {
+        start: (prefix * 5) as u32,
+        delete_count: ((old.len() - prefix - suffix) * 5) as u32,
+        data: Some(new[prefix..new.len() - suffix].to_vec()),
+    }]
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn handle_semantic_tokens_full(
+    server: &ForgeScriptServer,
+    params: SemanticTokensParams,
+) -> Result<Option<SemanticTokensResult>> {
+    let uri = &params.text_document.uri;
+    let data = document_tokens(server, uri, None)?;
+    Ok(Some(SemanticTokensResult::Tokens(remember_tokens(
+        server, uri, data,
+    ))))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn handle_semantic_tokens_full_delta(
+    server: &ForgeScriptServer,
+    params: SemanticTokensDeltaParams,
+) -> Result<Option<SemanticTokensFullDeltaResult>> {
+    let uri = &params.text_document.uri;
+    let data = document_tokens(server, uri, None)?;
+    let previous = server
+        .semantic_tokens
         .read()
         .expect("Server: lock poisoned")
-        .clone();
-    let tokens = extract_semantic_tokens_with_colors(&text, use_colors, &mgr);
-    Ok(Some(SemanticTokensResult::Tokens(SemanticTokens {
+        .get(uri)
+        .map(|(_, t)| t)
+        .filter(|t| t.result_id.as_ref() == Some(&params.previous_result_id))
+        .map(|t| t.data.clone());
+    let edits = previous.map(|old| token_edits(&old, &data));
+    let tokens = remember_tokens(server, uri, data);
+    Ok(Some(match edits {
+        Some(edits) => SemanticTokensFullDeltaResult::TokensDelta(SemanticTokensDelta {
+            result_id: tokens.result_id,
+            edits,
+        }),
+        // Unknown or outdated result ID: fall back to the full token set.
+        None => SemanticTokensFullDeltaResult::Tokens(tokens),
+    }))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn handle_semantic_tokens_range(
+    server: &ForgeScriptServer,
+    params: SemanticTokensRangeParams,
+) -> Result<Option<SemanticTokensRangeResult>> {
+    let data = document_tokens(server, &params.text_document.uri, Some(params.range))?;
+    Ok(Some(SemanticTokensRangeResult::Tokens(SemanticTokens {
         result_id: None,
-        data: tokens,
+        data,
     })))
 }
 #[cfg(not(target_arch = "wasm32"))]
 pub async fn handle_send_highlights(server: &ForgeScriptServer, uri: Url, text: &str) {
+    if !*server
+        .supports_forge_highlights
+        .read()
+        .expect("Server: lock poisoned")
+    {
+        return;
+    }
     let start = crate::utils::Instant::now();
     let highlights = {
-        let colors = server
-            .function_colors
-            .read()
-            .expect("Server: lock poisoned")
-            .clone();
-        if colors.is_empty() {
+        let settings = server.color_settings_for(&uri);
+        if settings.colors.is_empty() {
             return;
         }
 
-        let mgr = server
-            .manager
-            .read()
-            .expect("Server: lock poisoned")
-            .clone();
-        let consistent = *server
-            .consistent_function_colors
-            .read()
-            .expect("Server: lock poisoned");
-
-        extract_highlight_ranges(text, &colors, consistent, &mgr)
+        let parsed = cached_parse(server, &uri, text);
+        highlight_ranges_from_parse(text, &parsed, &settings.colors, settings.consistent)
             .into_iter()
             .map(|(start, end, color)| HighlightRange {
                 range: Range::new(
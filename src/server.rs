@@ -16,10 +16,16 @@ use crate::hover::handle_hover;
 use crate::metadata::MetadataManager;
 use crate::parser::{ForgeScriptParser, ParseResult};
 #[cfg(not(target_arch = "wasm32"))]
+use crate::progress::ProgressReporter;
+#[cfg(not(target_arch = "wasm32"))]
 use crate::utils::ForgeConfig;
 #[cfg(not(target_arch = "wasm32"))]
 use crate::utils::load_forge_config_full;
 #[cfg(not(target_arch = "wasm32"))]
+use crate::version::{ForgeVersion, check_function_versions};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::workspace::{WorkspaceFolderState, folder_config, folder_for, owning_folder};
+#[cfg(not(target_arch = "wasm32"))]
 use tower_lsp::Client;
 #[cfg(not(target_arch = "wasm32"))]
 use tower_lsp::LanguageServer;
@@ -59,41 +65,157 @@ pub struct CursorMovedParams {
     pub position: Position,
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddedDocumentsParams {
+    pub text_document: TextDocumentIdentifier,
+}
+
+/// A `${ ... }` expression exposed as a virtual JavaScript document.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddedDocument {
+    /// Virtual URI, unique per expression of the host document.
+    pub uri: String,
+    pub language_id: String,
+    /// The expression source, without the `${` and `}` delimiters.
+    pub content: String,
+    /// Where `content` sits in the host document.
+    pub range: Range,
+}
+
+/// Semantic tokens sent for a document, with the manager that produced them.
+#[cfg(not(target_arch = "wasm32"))]
+pub type SentTokens = (Arc<MetadataManager>, SemanticTokens);
+
 /// The core ForgeScript language server state.
+///
+/// Every field is shared, so clones are cheap handles onto the same server
+/// that background tasks can own.
 #[cfg(not(target_arch = "wasm32"))]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ForgeScriptServer {
     pub client: Client,
-    pub manager: Arc<RwLock<Arc<MetadataManager>>>,
     pub documents: Arc<RwLock<HashMap<Url, String>>>,
     pub parsed_cache: Arc<RwLock<HashMap<Url, ParseResult>>>,
+    /// Last semantic tokens sent per document, the base of delta requests.
+    pub semantic_tokens: Arc<RwLock<HashMap<Url, SentTokens>>>,
     pub workspace_folders: Arc<RwLock<Vec<PathBuf>>>,
-    pub multiple_function_colors: Arc<RwLock<bool>>,
-    pub consistent_function_colors: Arc<RwLock<bool>>,
-    pub function_colors: Arc<RwLock<Vec<String>>>,
-    pub config: Arc<RwLock<Option<ForgeConfig>>>,
     pub cursor_positions: Arc<RwLock<HashMap<Url, Position>>>,
+    pub supports_work_done_progress: Arc<RwLock<bool>>,
+    /// Whether the client only folds whole lines, per its `lineFoldingOnly` capability.
+    pub line_folding_only: Arc<RwLock<bool>>,
+    /// Whether the client opted into `forge/highlights` via `experimental.forgeHighlights`.
+    pub supports_forge_highlights: Arc<RwLock<bool>>,
+    /// Per-folder configuration and metadata, never empty: without workspace folders
+    /// it holds the server's working directory.
+    pub folder_states: Arc<RwLock<Vec<WorkspaceFolderState>>>,
+}
+
+/// Highlighting options resolved for a single document.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub struct ColorSettings {
+    pub multiple: bool,
+    pub consistent: bool,
+    pub colors: Vec<String>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ColorSettings {
+    /// Reads the highlighting options from a configuration, falling back to the defaults.
+    pub fn from_config(config: Option<&ForgeConfig>) -> Self {
+        Self {
+            multiple: config
+                .and_then(|c| c.multiple_function_colors)
+                .unwrap_or(true),
+            consistent: config
+                .and_then(|c| c.consistent_function_colors)
+                .unwrap_or(false),
+            colors: config
+                .and_then(|c| c.function_colors.clone())
+                .unwrap_or_default(),
+        }
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 impl ForgeScriptServer {
     /// Parses the updated text, updates the cache, and triggers diagnostic/highlight updates.
     pub async fn process_text(&self, uri: Url, text: String) {
+        if crate::config_schema::is_config_uri(&uri) {
+            let diagnostics = self.config_diagnostics(&uri, &text);
+            publish_diagnostics(self, &uri, &text, &diagnostics).await;
+            return;
+        }
+
         let start = crate::utils::Instant::now();
         crate::utils::forge_log(
             crate::utils::LogLevel::Debug,
             &format!("Processing text for {}", uri),
         );
-        let mgr_arc = self.manager.read().expect("Server: lock poisoned").clone();
+        let mgr_arc = self.manager_for(&uri);
+        // Tokens from another manager's metadata cannot serve as a delta base.
+        {
+            let mut semantic_tokens = self.semantic_tokens.write().expect("Server: lock poisoned");
+            if semantic_tokens
+                .get(&uri)
+                .is_some_and(|(mgr, _)| !Arc::ptr_eq(mgr, &mgr_arc))
+            {
+                semantic_tokens.remove(&uri);
+            }
+        }
         let parser = ForgeScriptParser::new(mgr_arc, &text);
-        let parsed = parser.parse();
+        let mut parsed = parser.parse();
+
+        if let Some(installed) = self.installed_version_for(&uri) {
+            parsed
+                .diagnostics
+                .extend(check_function_versions(&parsed.functions, installed));
+        }
 
         self.parsed_cache
             .write()
             .expect("Server: lock poisoned")
             .insert(uri.clone(), parsed.clone());
 
-        publish_diagnostics(self, &uri, &text, &parsed.diagnostics).await;
+        let mut diagnostics = parsed.diagnostics.clone();
+        if let Ok(path) = uri.to_file_path()
+            && let Some(mgr) = self.custom_functions_manager_for(&path)
+            && let Some(definition_problems) = mgr.custom_diagnostics_for(&path)
+        {
+            diagnostics.extend(definition_problems);
+        }
+        diagnostics
+            .extend(crate::variables::VariableIndex::build(&text, &parsed.functions).diagnostics());
+        diagnostics.extend(crate::control_flow::check_control_flow(
+            &text,
+            &parsed.functions,
+        ));
+        if matches!(
+            std::path::Path::new(uri.path())
+                .extension()
+                .and_then(|e| e.to_str()),
+            Some("js" | "ts")
+        ) {
+            let events = self
+                .manager_for(&uri)
+                .events
+                .read()
+                .expect("Server: lock poisoned")
+                .clone();
+            let client = self.client_setup_for(&uri);
+            diagnostics.extend(crate::events::check_command_events(
+                &text,
+                &events,
+                client.as_ref().map(|(path, setup)| (path.as_path(), setup)),
+            ));
+        }
+        let suppressed = self.suppressed_diagnostics_for(&uri);
+        diagnostics.retain(|d| d.code.as_ref().is_none_or(|c| !suppressed.contains(c)));
+        publish_diagnostics(self, &uri, &text, &diagnostics).await;
         crate::semantic::handle_send_highlights(self, uri.clone(), &text).await;
         crate::depth::handle_update_depth(self, uri.clone()).await;
 
@@ -103,12 +225,475 @@ impl ForgeScriptServer {
         );
     }
 
-    /// Returns the total number of functions currently indexed by the metadata manager.
-    pub fn function_count(&self) -> usize {
-        self.manager
+    /// Re-detects the installed ForgeScript version and re-validates open documents if it changed.
+    pub async fn refresh_installed_version(&self) {
+        let mut changed = false;
+        for state in self
+            .folder_states
+            .write()
+            .expect("Server: lock poisoned")
+            .iter_mut()
+        {
+            let version =
+                crate::version::detect_installed_version(std::slice::from_ref(&state.root));
+            if state.installed_version == version {
+                continue;
+            }
+            crate::utils::forge_log(
+                crate::utils::LogLevel::Info,
+                &format!(
+                    "Installed ForgeScript version in {} changed to {}",
+                    state.root.display(),
+                    version.map_or_else(|| "unknown".to_string(), |v| v.to_string())
+                ),
+            );
+            state.installed_version = version;
+            changed = true;
+        }
+        if changed {
+            self.reprocess_open_documents().await;
+        }
+    }
+
+    /// Validates every `forgeconfig.json` on disk in the workspace and publishes the results.
+    pub async fn publish_config_diagnostics(&self) {
+        let folders = self
+            .workspace_folders
+            .read()
+            .expect("Server: lock poisoned")
+            .clone();
+        for path in folders
+            .iter()
+            .flat_map(|f| crate::utils::config_file_paths(f))
+        {
+            let (Ok(text), Ok(uri)) = (std::fs::read_to_string(&path), Url::from_file_path(&path))
+            else {
+                continue;
+            };
+            let diagnostics = self.config_diagnostics(&uri, &text);
+            publish_diagnostics(self, &uri, &text, &diagnostics).await;
+        }
+    }
+
+    /// Validates a `forgeconfig.json` and reports the name conflicts its definitions cause.
+    fn config_diagnostics(&self, uri: &Url, text: &str) -> Vec<crate::parser::Diagnostic> {
+        let mut diagnostics = crate::config_schema::validate_config(text);
+        let config_dir = uri
+            .to_file_path()
+            .ok()
+            .and_then(|path| path.parent().map(|dir| dir.to_path_buf()));
+        let manager = self
+            .folder_states
             .read()
             .expect("Server: lock poisoned")
-            .function_count()
+            .iter()
+            .find(|state| config_dir.is_some() && state.config_dir == config_dir)
+            .map(|state| state.manager.clone());
+        if let Some(mgr) = manager {
+            diagnostics.extend(crate::config_schema::conflict_diagnostics(
+                text,
+                &mgr.conflicts(),
+            ));
+        }
+        diagnostics
+    }
+
+    /// Collects the definition problems of every scanned custom function file, across all scopes.
+    fn custom_function_diagnostics(&self) -> HashMap<PathBuf, Vec<crate::parser::Diagnostic>> {
+        let mut managers: Vec<Arc<MetadataManager>> = Vec::new();
+        for state in self
+            .folder_states
+            .read()
+            .expect("Server: lock poisoned")
+            .iter()
+        {
+            if !managers.iter().any(|m| Arc::ptr_eq(m, &state.manager)) {
+                managers.push(state.manager.clone());
+            }
+        }
+
+        let mut files: HashMap<PathBuf, Vec<crate::parser::Diagnostic>> = HashMap::new();
+        for mgr in managers {
+            for (path, diagnostics) in mgr.custom_function_diagnostics() {
+                files.entry(path).or_default().extend(diagnostics);
+            }
+        }
+        files
+    }
+
+    /// Publishes the definition problems found in every scanned custom function file
+    /// that is not open.
+    ///
+    /// Open files get theirs from `process_text`, merged with their other diagnostics,
+    /// so callers re-process open documents afterwards.
+    pub async fn publish_custom_function_diagnostics(&self) {
+        for (path, diagnostics) in self.custom_function_diagnostics() {
+            let Ok(uri) = Url::from_file_path(&path) else {
+                continue;
+            };
+            if self
+                .documents
+                .read()
+                .expect("Server: lock poisoned")
+                .contains_key(&uri)
+            {
+                continue;
+            }
+            let text = std::fs::read_to_string(&path).unwrap_or_default();
+            publish_diagnostics(self, &uri, &text, &diagnostics).await;
+        }
+    }
+
+    /// Runs `f` against the workspace folder responsible for `uri`.
+    fn with_folder<T>(&self, uri: &Url, f: impl FnOnce(&WorkspaceFolderState) -> T) -> T {
+        let path = uri.to_file_path().ok();
+        let states = self.folder_states.read().expect("Server: lock poisoned");
+        f(folder_for(&states, path.as_deref()).expect("Server: no workspace folder state"))
+    }
+
+    /// Returns the metadata manager responsible for a document.
+    pub fn manager_for(&self, uri: &Url) -> Arc<MetadataManager> {
+        self.with_folder(uri, |f| f.manager.clone())
+    }
+
+    /// Returns the ForgeScript version installed in the document's workspace folder.
+    pub fn installed_version_for(&self, uri: &Url) -> Option<ForgeVersion> {
+        self.with_folder(uri, |f| f.installed_version)
+    }
+
+    /// Returns the client setup configured for the document's workspace folder.
+    pub fn client_setup_for(&self, uri: &Url) -> Option<(PathBuf, crate::custom_js::ClientSetup)> {
+        self.with_folder(uri, |f| f.client_setup.clone())
+    }
+
+    /// Returns the diagnostic codes the document's configuration suppresses.
+    pub fn suppressed_diagnostics_for(&self, uri: &Url) -> Vec<String> {
+        self.with_folder(uri, |f| {
+            f.config
+                .as_ref()
+                .and_then(|c| c.suppressed_diagnostics.clone())
+                .unwrap_or_default()
+        })
+    }
+
+    /// Returns the highlighting options that apply to a document.
+    pub fn color_settings_for(&self, uri: &Url) -> ColorSettings {
+        self.with_folder(uri, |f| ColorSettings::from_config(f.config.as_ref()))
+    }
+
+    /// Re-reads `forgeconfig.json` and applies it without restarting the server.
+    ///
+    /// Folders whose metadata sources changed are rebuilt from cache and refreshed in the
+    /// background; otherwise custom functions are swapped in place and open documents are
+    /// re-validated. A deleted or invalid config falls back to the defaults.
+    pub async fn reload_config(&self) {
+        let folders = self
+            .workspace_folders
+            .read()
+            .expect("Server: lock poisoned")
+            .clone();
+        let fallback = load_forge_config_full(&folders);
+
+        let mut needs_fetch = false;
+        {
+            let mut states = self.folder_states.write().expect("Server: lock poisoned");
+            let mut reloaded_states: Vec<WorkspaceFolderState> = Vec::with_capacity(states.len());
+            for state in states.iter() {
+                let reloaded = folder_config(&state.root, fallback.as_ref());
+                let new_urls = reloaded.as_ref().map(|(c, _)| c.urls.as_slice());
+                if state.urls() != new_urls {
+                    // Serve the new sources from cache until the background refresh lands.
+                    match WorkspaceFolderState::load(
+                        state.root.clone(),
+                        fallback.as_ref(),
+                        &reloaded_states,
+                        Some(self.client.clone()),
+                    ) {
+                        Ok(reloaded) => {
+                            needs_fetch = true;
+                            reloaded_states.push(reloaded);
+                        }
+                        Err(e) => {
+                            crate::utils::forge_log(
+                                crate::utils::LogLevel::Error,
+                                &format!("Metadata initialization failed: {e}"),
+                            );
+                            reloaded_states.push(state.clone());
+                        }
+                    }
+                    continue;
+                }
+
+                let mut state = state.clone();
+                if let Some((config, config_dir)) = reloaded {
+                    // Folders sharing a manager only reload its custom functions once.
+                    let reloaded_before = reloaded_states
+                        .iter()
+                        .any(|other| Arc::ptr_eq(&other.manager, &state.manager));
+                    if !reloaded_before
+                        && let Err(e) = state
+                            .manager
+                            .reload_custom_functions_from_config(&config, &config_dir)
+                    {
+                        crate::utils::forge_log(
+                            crate::utils::LogLevel::Warn,
+                            &format!(
+                                "Custom function reload failed for {}: {e}",
+                                state.root.display()
+                            ),
+                        );
+                    }
+                    state.config = Some(config);
+                    state.config_dir = Some(config_dir);
+                    state.refresh_client_setup();
+                }
+                reloaded_states.push(state);
+            }
+            *states = reloaded_states;
+        }
+        crate::utils::forge_log(
+            crate::utils::LogLevel::Info,
+            &format!("Reloaded configuration (sources changed: {needs_fetch})"),
+        );
+
+        self.publish_config_diagnostics().await;
+        self.publish_custom_function_diagnostics().await;
+        if needs_fetch {
+            let server = self.clone();
+            tokio::spawn(async move {
+                server.refresh_metadata().await;
+            });
+            return;
+        }
+        self.reprocess_open_documents().await;
+    }
+
+    /// Starts a work-done progress on the client, if it advertised support for it.
+    pub async fn begin_progress(&self, title: &str) -> ProgressReporter {
+        let enabled = *self
+            .supports_work_done_progress
+            .read()
+            .expect("Server: lock poisoned");
+        ProgressReporter::begin(&self.client, enabled, title).await
+    }
+
+    /// Fetches all metadata sources into fresh managers, swaps them in and re-validates open documents.
+    ///
+    /// Requests keep being served from the previous (cache-backed) managers while this runs.
+    pub async fn refresh_metadata(&self) {
+        let start = crate::utils::Instant::now();
+        // Folders sharing a configuration directory share one refreshed manager.
+        let mut scopes: Vec<WorkspaceFolderState> = Vec::new();
+        for state in self
+            .folder_states
+            .read()
+            .expect("Server: lock poisoned")
+            .iter()
+        {
+            if scopes
+                .iter()
+                .all(|scope| scope.config_dir != state.config_dir)
+            {
+                scopes.push(state.clone());
+            }
+        }
+        for scope in scopes {
+            let loaded = scope
+                .loaded_config()
+                .map(|(config, dir)| (config.clone(), dir.to_path_buf()));
+            let Some(manager) = self.fetch_manager(loaded.as_ref()).await else {
+                continue;
+            };
+            let manager = Arc::new(manager);
+            for state in self
+                .folder_states
+                .write()
+                .expect("Server: lock poisoned")
+                .iter_mut()
+                .filter(|state| state.config_dir == scope.config_dir)
+            {
+                state.manager = manager.clone();
+            }
+        }
+
+        crate::utils::forge_log(
+            crate::utils::LogLevel::Info,
+            &format!(
+                "Background metadata refresh finished in {}",
+                start.elapsed_display()
+            ),
+        );
+        self.publish_config_diagnostics().await;
+        self.publish_custom_function_diagnostics().await;
+        self.reprocess_open_documents().await;
+    }
+
+    /// Builds a manager for one configuration scope from the network, reporting progress.
+    ///
+    /// Returns `None` when fetching fails, so the caller keeps its cached manager.
+    async fn fetch_manager(
+        &self,
+        loaded: Option<&(ForgeConfig, PathBuf)>,
+    ) -> Option<MetadataManager> {
+        let (urls, cache_ttl) = match loaded {
+            Some((config, _)) => (config.urls.clone(), config.cache_ttl()),
+            None => (
+                vec![crate::metadata::DEFAULT_FUNCTIONS_URL.to_string()],
+                None,
+            ),
+        };
+
+        let manager =
+            match MetadataManager::new("./.cache", urls, Some(self.client.clone()), cache_ttl) {
+                Ok(manager) => manager,
+                Err(e) => {
+                    crate::utils::forge_log(
+                        crate::utils::LogLevel::Error,
+                        &format!("Metadata initialization failed: {e}"),
+                    );
+                    return None;
+                }
+            };
+
+        let progress = self.begin_progress("Fetching ForgeScript metadata").await;
+        let report = |url: &str, done: usize, total: usize| {
+            let file = url.rsplit('/').next().unwrap_or(url);
+            progress.report_step(done, total, format!("{done}/{total} {file}"));
+        };
+        if let Err(e) = manager.load_all_with_progress(&report).await {
+            crate::utils::forge_log(
+                crate::utils::LogLevel::Warn,
+                &format!("Background metadata refresh failed, keeping cached data: {e}"),
+            );
+            progress.end(format!("Failed: {e}"));
+            return None;
+        }
+        progress.end(format!(
+            "Indexed {} functions from {} sources",
+            manager.function_count(),
+            manager.source_count()
+        ));
+
+        if let Some((config, config_dir)) = loaded
+            && (config.custom_functions.is_some() || config.custom_functions_path.is_some())
+        {
+            let scan = self.begin_progress("Scanning custom functions").await;
+            let report = |path: &std::path::Path, done: usize, total: usize| {
+                let file = path.file_name().map_or_else(
+                    || path.display().to_string(),
+                    |name| name.to_string_lossy().into_owned(),
+                );
+                scan.report_step(done, total, format!("{done}/{total} {file}"));
+            };
+            match manager
+                .load_custom_functions_from_config_with_progress(config, config_dir, &report)
+            {
+                Ok((files, count)) => {
+                    scan.end(format!(
+                        "Loaded {count} custom functions from {files} files"
+                    ));
+                }
+                Err(e) => {
+                    crate::utils::forge_log(
+                        crate::utils::LogLevel::Warn,
+                        &format!("Custom function load failed: {e}"),
+                    );
+                    scan.end(format!("Failed: {e}"));
+                }
+            }
+        }
+
+        crate::utils::forge_log(
+            crate::utils::LogLevel::Info,
+            &format!(
+                "Indexed {} functions for {}",
+                manager.function_count(),
+                loaded.map_or_else(
+                    || "the default scope".to_string(),
+                    |(_, dir)| dir.display().to_string()
+                )
+            ),
+        );
+        Some(manager)
+    }
+
+    /// Returns the manager that owns custom functions defined in `path`, if any.
+    fn custom_functions_manager_for(&self, path: &std::path::Path) -> Option<Arc<MetadataManager>> {
+        let states = self.folder_states.read().expect("Server: lock poisoned");
+        owning_folder(&states, path)
+            .into_iter()
+            .chain(states.iter())
+            .find(|state| state.owns_custom_function_file(path))
+            .map(|state| state.manager.clone())
+    }
+
+    /// Re-parses every open document and republishes its diagnostics and highlights.
+    pub async fn reprocess_open_documents(&self) {
+        let docs = self
+            .documents
+            .read()
+            .expect("Server: lock poisoned")
+            .clone();
+        if docs.is_empty() {
+            return;
+        }
+
+        let progress = self.begin_progress("Indexing ForgeScript documents").await;
+        let total = docs.len();
+        for (done, (uri, text)) in docs.into_iter().enumerate() {
+            progress.report_step(done, total, format!("{done}/{total} documents"));
+            self.process_text(uri, text).await;
+        }
+        progress.end(format!("Indexed {total} documents"));
+    }
+
+    /// Handles `forge/embeddedDocuments`: lists the document's JavaScript expressions so the
+    /// client can hand them to its TypeScript server.
+    pub async fn embedded_documents(
+        &self,
+        params: EmbeddedDocumentsParams,
+    ) -> Result<Vec<EmbeddedDocument>> {
+        let uri = params.text_document.uri;
+        let text = self
+            .documents
+            .read()
+            .expect("Server: lock poisoned")
+            .get(&uri)
+            .cloned()
+            .ok_or(tower_lsp::jsonrpc::Error::invalid_params(
+                "Document not found",
+            ))?;
+        let tokens = self
+            .parsed_cache
+            .read()
+            .expect("Server: lock poisoned")
+            .get(&uri)
+            .map(|parsed| parsed.tokens.clone())
+            .unwrap_or_default();
+
+        Ok(tokens
+            .iter()
+            .filter(|t| t.kind == crate::parser::TokenKind::JavaScript)
+            .enumerate()
+            .map(|(index, token)| {
+                let (start, end) = (token.start + 2, token.end - 1);
+                EmbeddedDocument {
+                    uri: format!("{uri}.embedded-{index}.js"),
+                    language_id: "javascript".to_string(),
+                    content: text[start..end].to_string(),
+                    range: Range::new(
+                        crate::utils::offset_to_position(&text, start),
+                        crate::utils::offset_to_position(&text, end),
+                    ),
+                }
+            })
+            .collect())
+    }
+
+    /// Returns the number of functions indexed for documents outside any workspace folder.
+    pub fn function_count(&self) -> usize {
+        let states = self.folder_states.read().expect("Server: lock poisoned");
+        folder_for(&states, None).map_or(0, |state| state.manager.function_count())
     }
 }
 
@@ -143,6 +728,9 @@ fn build_capabilities() -> ServerCapabilities {
         hover_provider: Some(HoverProviderCapability::Simple(true)),
         definition_provider: Some(OneOf::Left(true)),
         folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+        color_provider: Some(ColorProviderCapability::Simple(true)),
+        document_highlight_provider: Some(OneOf::Left(true)),
+        selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
         completion_provider: Some(CompletionOptions {
             resolve_provider: Some(false),
             trigger_characters: Some(vec![
@@ -177,15 +765,21 @@ fn build_capabilities() -> ServerCapabilities {
                         SemanticTokenType::PARAMETER,
                         SemanticTokenType::STRING,
                         SemanticTokenType::COMMENT,
+                        SemanticTokenType::OPERATOR,
+                        SemanticTokenType::VARIABLE,
+                        SemanticTokenType::ENUM_MEMBER,
                     ],
-                    token_modifiers: vec![],
+                    token_modifiers: crate::semantic::TOKEN_MODIFIERS.to_vec(),
                 },
-                range: Some(false),
-                full: Some(SemanticTokensFullOptions::Bool(true)),
+                range: Some(true),
+                full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
             },
         )),
         execute_command_provider: Some(ExecuteCommandOptions {
-            commands: vec!["forge/cursorMoved".to_string()],
+            commands: vec![
+                "forge/cursorMoved".to_string(),
+                "forge/configSchema".to_string(),
+            ],
             ..Default::default()
         }),
         workspace: Some(WorkspaceServerCapabilities {
@@ -203,42 +797,67 @@ fn build_capabilities() -> ServerCapabilities {
 #[async_trait]
 impl LanguageServer for ForgeScriptServer {
     async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
-        if let Some(folders) = params.workspace_folders {
-            let paths = folders
-                .into_iter()
-                .filter_map(|f| f.uri.to_file_path().ok())
-                .collect::<Vec<_>>();
-            self.workspace_folders
-                .write()
-                .expect("Server: lock poisoned")
-                .clone_from(&paths);
+        *self
+            .supports_work_done_progress
+            .write()
+            .expect("Server: lock poisoned") = params
+            .capabilities
+            .window
+            .as_ref()
+            .and_then(|w| w.work_done_progress)
+            .unwrap_or(false);
+        *self
+            .line_folding_only
+            .write()
+            .expect("Server: lock poisoned") = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|t| t.folding_range.as_ref())
+            .and_then(|f| f.line_folding_only)
+            .unwrap_or(false);
+        *self
+            .supports_forge_highlights
+            .write()
+            .expect("Server: lock poisoned") = params
+            .capabilities
+            .experimental
+            .as_ref()
+            .and_then(|e| e.get("forgeHighlights"))
+            .and_then(serde_json::Value::as_bool)
+            .unwrap_or(false);
 
-            if let Some((config, config_path)) = load_forge_config_full(&paths) {
-                let manager = MetadataManager::new(
-                    "./.cache",
-                    config.urls.clone(),
+        let paths = params
+            .workspace_folders
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|f| f.uri.to_file_path().ok())
+            .collect::<Vec<_>>();
+        if !paths.is_empty() {
+            // Remote sources are refreshed in the background after `initialized`.
+            let fallback = load_forge_config_full(&paths);
+            let mut states: Vec<WorkspaceFolderState> = Vec::with_capacity(paths.len());
+            for root in &paths {
+                match WorkspaceFolderState::load(
+                    root.clone(),
+                    fallback.as_ref(),
+                    &states,
                     Some(self.client.clone()),
-                )
-                .expect("Metadata initialization failed");
-                manager.load_all().await.expect("Metadata load failed");
-                manager
-                    .load_custom_functions_from_config(&config, &config_path)
-                    .expect("Custom function load failed");
-
-                *self.manager.write().expect("Server: lock poisoned") = Arc::new(manager);
-                if let Some(use_colors) = config.multiple_function_colors {
-                    *self
-                        .multiple_function_colors
-                        .write()
-                        .expect("Server: lock poisoned") = use_colors;
-                }
-                if let Some(consistent) = config.consistent_function_colors {
-                    *self
-                        .consistent_function_colors
-                        .write()
-                        .expect("Server: lock poisoned") = consistent;
+                ) {
+                    Ok(state) => states.push(state),
+                    Err(e) => crate::utils::forge_log(
+                        crate::utils::LogLevel::Error,
+                        &format!("Metadata initialization failed for {}: {e}", root.display()),
+                    ),
                 }
             }
+            if !states.is_empty() {
+                self.workspace_folders
+                    .write()
+                    .expect("Server: lock poisoned")
+                    .clone_from(&paths);
+                *self.folder_states.write().expect("Server: lock poisoned") = states;
+            }
         }
         Ok(InitializeResult {
             capabilities: build_capabilities(),
@@ -260,16 +879,35 @@ impl LanguageServer for ForgeScriptServer {
                 method: "workspace/didChangeWatchedFiles".to_string(),
                 register_options: Some(
                     serde_json::to_value(DidChangeWatchedFilesRegistrationOptions {
-                        watchers: vec![FileSystemWatcher {
-                            glob_pattern: GlobPattern::String("**/*.{js,ts}".to_string()),
-                            kind: Some(WatchKind::all()),
-                        }],
+                        watchers: vec![
+                            FileSystemWatcher {
+                                glob_pattern: GlobPattern::String("**/*.{js,ts}".to_string()),
+                                kind: Some(WatchKind::all()),
+                            },
+                            FileSystemWatcher {
+                                glob_pattern: GlobPattern::String("**/package.json".to_string()),
+                                kind: Some(WatchKind::all()),
+                            },
+                            FileSystemWatcher {
+                                glob_pattern: GlobPattern::String(
+                                    "**/forgeconfig.json".to_string(),
+                                ),
+                                kind: Some(WatchKind::all()),
+                            },
+                        ],
                     })
                     .expect("Server: serialization failure"),
                 ),
             }])
             .await
             .ok();
+        self.publish_config_diagnostics().await;
+        self.publish_custom_function_diagnostics().await;
+
+        let server = self.clone();
+        tokio::spawn(async move {
+            server.refresh_metadata().await;
+        });
     }
 
     async fn shutdown(&self) -> Result<()> {
@@ -298,6 +936,22 @@ impl LanguageServer for ForgeScriptServer {
         }
     }
 
+    async fn did_close(&self, params: DidCloseTextDocumentParams) {
+        let uri = params.text_document.uri;
+        self.documents
+            .write()
+            .expect("Server: lock poisoned")
+            .remove(&uri);
+        self.parsed_cache
+            .write()
+            .expect("Server: lock poisoned")
+            .remove(&uri);
+        self.semantic_tokens
+            .write()
+            .expect("Server: lock poisoned")
+            .remove(&uri);
+    }
+
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
         handle_hover(self, params).await
     }
@@ -313,6 +967,31 @@ impl LanguageServer for ForgeScriptServer {
         crate::folding_range::handle_folding_range(self, params).await
     }
 
+    async fn document_highlight(
+        &self,
+        params: DocumentHighlightParams,
+    ) -> Result<Option<Vec<DocumentHighlight>>> {
+        crate::document_highlight::handle_document_highlight(self, params).await
+    }
+
+    async fn selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> Result<Option<Vec<SelectionRange>>> {
+        crate::selection_range::handle_selection_range(self, params).await
+    }
+
+    async fn document_color(&self, params: DocumentColorParams) -> Result<Vec<ColorInformation>> {
+        crate::color::handle_document_color(self, params).await
+    }
+
+    async fn color_presentation(
+        &self,
+        params: ColorPresentationParams,
+    ) -> Result<Vec<ColorPresentation>> {
+        crate::color::handle_color_presentation(self, params).await
+    }
+
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
         crate::completion::handle_completion(self, params).await
     }
@@ -328,6 +1007,20 @@ impl LanguageServer for ForgeScriptServer {
         crate::semantic::handle_semantic_tokens_full(self, params).await
     }
 
+    async fn semantic_tokens_full_delta(
+        &self,
+        params: SemanticTokensDeltaParams,
+    ) -> Result<Option<SemanticTokensFullDeltaResult>> {
+        crate::semantic::handle_semantic_tokens_full_delta(self, params).await
+    }
+
+    async fn semantic_tokens_range(
+        &self,
+        params: SemanticTokensRangeParams,
+    ) -> Result<Option<SemanticTokensRangeResult>> {
+        crate::semantic::handle_semantic_tokens_range(self, params).await
+    }
+
     async fn execute_command(
         &self,
         params: ExecuteCommandParams,
@@ -335,49 +1028,142 @@ impl LanguageServer for ForgeScriptServer {
         crate::commands::handle_execute_command(self, params).await
     }
 
-    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
-        let mgr_outer = self.manager.read().expect("Server: lock poisoned").clone();
-        let mgr = mgr_outer.as_ref();
-        let config = self.config.read().expect("Server: lock poisoned");
-        let ws_folders = self
-            .workspace_folders
-            .read()
-            .expect("Server: lock poisoned");
-        let rel_path = config
-            .as_ref()
-            .and_then(|c| c.custom_functions_path.as_ref());
+    async fn did_change_configuration(&self, _: DidChangeConfigurationParams) {
+        self.reload_config().await;
+    }
 
-        for change in params.changes {
-            if let Ok(path) = change.uri.to_file_path() {
-                let mut is_allowed = false;
-                if let Some(rel) = rel_path {
-                    for root in ws_folders.iter() {
-                        if path.starts_with(root.join(rel)) {
-                            is_allowed = true;
-                            break;
-                        }
-                    }
-                }
-                if !is_allowed {
-                    continue;
-                }
-                if !matches!(
-                    path.extension().and_then(|s| s.to_str()),
-                    Some("js") | Some("ts")
+    async fn did_change_workspace_folders(&self, params: DidChangeWorkspaceFoldersParams) {
+        let to_paths = |folders: Vec<WorkspaceFolder>| {
+            folders
+                .into_iter()
+                .filter_map(|f| f.uri.to_file_path().ok())
+                .collect::<Vec<_>>()
+        };
+        let removed = to_paths(params.event.removed);
+        let added = to_paths(params.event.added);
+
+        let folders = {
+            let mut folders = self
+                .workspace_folders
+                .write()
+                .expect("Server: lock poisoned");
+            folders.retain(|root| !removed.contains(root));
+            folders.extend(added.iter().cloned());
+            folders.clone()
+        };
+        let fallback = load_forge_config_full(&folders);
+        let mut needs_fetch = false;
+        {
+            let mut states = self.folder_states.write().expect("Server: lock poisoned");
+            states.retain(|state| !removed.contains(&state.root));
+            let mut roots = added;
+            if states.is_empty()
+                && roots.is_empty()
+                && let Ok(cwd) = std::env::current_dir()
+            {
+                // Without workspace folders the working directory is the implicit root.
+                roots.push(cwd);
+            }
+            for root in roots {
+                match WorkspaceFolderState::load(
+                    root.clone(),
+                    fallback.as_ref(),
+                    &states,
+                    Some(self.client.clone()),
                 ) {
-                    continue;
+                    Ok(state) => {
+                        needs_fetch |= !states
+                            .iter()
+                            .any(|s| Arc::ptr_eq(&s.manager, &state.manager));
+                        states.push(state);
+                    }
+                    Err(e) => crate::utils::forge_log(
+                        crate::utils::LogLevel::Error,
+                        &format!("Metadata initialization failed for {}: {e}", root.display()),
+                    ),
                 }
+            }
+        }
+        crate::utils::forge_log(
+            crate::utils::LogLevel::Info,
+            &format!(
+                "Workspace folders changed: {} removed, {} now open",
+                removed.len(),
+                self.workspace_folders
+                    .read()
+                    .expect("Server: lock poisoned")
+                    .len()
+            ),
+        );
+        self.publish_custom_function_diagnostics().await;
 
-                match change.typ {
-                    FileChangeType::CREATED | FileChangeType::CHANGED => {
-                        if let Ok(_count) = mgr.reload_file(path.clone()) {}
-                    }
-                    FileChangeType::DELETED => {
-                        mgr.remove_functions_at_path(&path);
-                    }
-                    _ => {}
+        if needs_fetch {
+            let server = self.clone();
+            tokio::spawn(async move {
+                server.refresh_metadata().await;
+            });
+            return;
+        }
+        self.reprocess_open_documents().await;
+    }
+
+    async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
+        let changed_file = |name: &str| {
+            params.changes.iter().any(|c| {
+                c.uri
+                    .to_file_path()
+                    .is_ok_and(|p| p.file_name().is_some_and(|n| n == name))
+            })
+        };
+        if changed_file("forgeconfig.json") {
+            self.reload_config().await;
+        }
+        if changed_file("package.json") {
+            self.refresh_installed_version().await;
+        }
+
+        let mut custom_files_changed = false;
+        let mut client_setup_changed = false;
+        for change in params.changes {
+            let Ok(path) = change.uri.to_file_path() else {
+                continue;
+            };
+            if !matches!(
+                path.extension().and_then(|s| s.to_str()),
+                Some("js") | Some("ts")
+            ) {
+                continue;
+            }
+            for state in self
+                .folder_states
+                .write()
+                .expect("Server: lock poisoned")
+                .iter_mut()
+                .filter(|state| state.client_path().as_deref() == Some(path.as_path()))
+            {
+                state.refresh_client_setup();
+                client_setup_changed = true;
+            }
+            let Some(mgr) = self.custom_functions_manager_for(&path) else {
+                continue;
+            };
+            custom_files_changed = true;
+
+            match change.typ {
+                FileChangeType::CREATED | FileChangeType::CHANGED => {
+                    if let Ok(_count) = mgr.reload_file(path.clone()) {}
                 }
+                FileChangeType::DELETED => {
+                    mgr.remove_functions_at_path(&path);
+                }
+                _ => {}
             }
         }
+        if custom_files_changed {
+            self.publish_custom_function_diagnostics().await;
+        }
+        if custom_files_changed || client_setup_changed {
+            self.reprocess_open_documents().await;
+        }
     }
 }
@@ -25,11 +25,7 @@ pub async fn handle_signature_help(
     };
     let param_idx = compute_active_param_index(&text_up_to_cursor[open_idx + 1..]);
 
-    let mgr = server
-        .manager
-        .read()
-        .expect("Server: lock poisoned")
-        .clone();
+    let mgr = server.manager_for(&uri);
     if let Some(func) = mgr.get(&format!("${func_name}")) {
         let sig = SignatureInformation {
             label: func.signature_label(),
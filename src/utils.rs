@@ -10,6 +10,7 @@ use std::path::PathBuf;
 
 use lsp_types::*;
 use regex::Regex;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 #[cfg(not(target_arch = "wasm32"))]
@@ -20,7 +21,9 @@ pub(crate) static SIGNATURE_FUNC_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"\$([a-zA-Z_][a-zA-Z0-9_]*)\s*$").expect("Server: regex failure"));
 
 /// Available log levels for ForgeLSP.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, JsonSchema,
+)]
 #[serde(rename_all = "lowercase")]
 pub enum LogLevel {
     Trace = 0,
@@ -156,40 +159,55 @@ impl Instant {
 // ── Data Types ──────────────────────────────────────────────────────────────
 
 /// Parameters for project-specific custom function definitions.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct CustomFunctionParam {
     pub name: String,
     #[serde(default)]
     pub description: Option<String>,
+    /// Argument type, e.g. `String`, `Number` or `Boolean`.
     #[serde(rename = "type")]
     pub param_type: String,
     #[serde(default)]
     pub required: Option<bool>,
+    /// Whether the argument consumes all remaining values.
     #[serde(default)]
     pub rest: Option<bool>,
+    /// Allowed values for the argument.
     #[serde(default)]
     pub arg_enum: Option<Vec<String>>,
+    /// Name of a metadata enum listing the allowed values.
     #[serde(default)]
     pub enum_name: Option<String>,
 }
 
 /// Structure representing a user-defined function in ForgeLSP.
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct CustomFunction {
+    /// Function name, with or without the leading `$`.
     pub name: String,
     #[serde(default)]
     pub description: Option<String>,
+    /// Arguments, either as parameter objects or as plain argument names.
     #[serde(default)]
+    #[schemars(schema_with = "custom_params_schema")]
     pub params: Option<JsonValue>,
     #[serde(default)]
     pub brackets: Option<bool>,
     #[serde(default)]
     pub alias: Option<Vec<String>>,
+    /// Source location, filled in for functions scanned from JS/TS files.
     #[serde(default)]
+    #[schemars(skip)]
     pub path: Option<String>,
     #[serde(default)]
+    #[schemars(skip)]
     pub line: Option<u32>,
     #[serde(default)]
+    #[schemars(skip)]
+    pub column: Option<u32>,
+    #[serde(default)]
     pub output: Option<Vec<String>>,
 }
 
@@ -203,42 +221,89 @@ pub struct Event {
     pub intents: Option<Vec<String>>,
 }
 
+fn custom_params_schema(generator: &mut schemars::SchemaGenerator) -> schemars::Schema {
+    let param = generator.subschema_for::<CustomFunctionParam>();
+    schemars::json_schema!({
+        "type": ["array", "null"],
+        "items": { "anyOf": [{ "type": "string" }, param] }
+    })
+}
+
+/// Which definition wins when a custom function shares a name or alias with a built-in one.
+///
+/// `custom` (the default) lets custom functions override built-ins; `builtin` keeps the
+/// built-in and ignores the conflicting custom definition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FunctionPrecedence {
+    #[default]
+    Custom,
+    Builtin,
+}
+
 /// Configuration structure for ForgeLSP, typically loaded from forgeconfig.json.
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, JsonSchema)]
+#[schemars(deny_unknown_fields)]
 pub struct ForgeConfig {
+    /// Metadata sources: URLs or `github:owner/repo[#branch]` shorthands.
     pub urls: Vec<String>,
+    /// Color each function name differently in `forge/highlights`.
     #[serde(default)]
     pub multiple_function_colors: Option<bool>,
+    /// Palette used for function highlighting.
     #[serde(default)]
     pub function_colors: Option<Vec<String>>,
+    /// Give every call of the same function the same color.
     #[serde(default)]
     pub consistent_function_colors: Option<bool>,
+    /// Custom functions declared inline.
     #[serde(default)]
     pub custom_functions: Option<Vec<CustomFunction>>,
+    /// Directory, relative to this file, scanned for custom functions in JS/TS files.
     #[serde(default)]
     pub custom_functions_path: Option<String>,
     #[serde(default)]
     pub log_level: Option<LogLevel>,
+    /// Seconds a cached metadata file is served without revalidating it over the network.
+    #[serde(default)]
+    pub cache_ttl: Option<u64>,
+    /// Whether custom or built-in functions win when their names or aliases collide.
+    #[serde(default)]
+    pub function_precedence: Option<FunctionPrecedence>,
+    /// Client setup file, relative to this file, whose `new ForgeClient({...})` enables intents.
+    #[serde(default)]
+    pub client_path: Option<String>,
+    /// Codes of optional checks to hide, such as `orphan-else` or `invalid-condition`.
+    #[serde(default)]
+    pub suppressed_diagnostics: Option<Vec<String>>,
+}
+
+impl ForgeConfig {
+    /// Returns the configured metadata cache TTL, if any.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn cache_ttl(&self) -> Option<std::time::Duration> {
+        self.cache_ttl.map(std::time::Duration::from_secs)
+    }
 }
 
 // ── Config Loading (Native) ─────────────────────────────────────────────────
 
-/// Attempts to find and load the ForgeLSP configuration from the workspace.
+/// Locations where a workspace folder may keep its forgeconfig.json, in lookup order.
 #[cfg(not(target_arch = "wasm32"))]
-pub fn load_forge_config(workspace_folders: &[PathBuf]) -> Option<Vec<String>> {
-    load_forge_config_full(workspace_folders).map(|(cfg, _)| cfg.urls)
+pub fn config_file_paths(folder: &std::path::Path) -> [PathBuf; 2] {
+    [
+        folder.join(crate::config_schema::CONFIG_FILE_NAME),
+        folder
+            .join(".vscode")
+            .join(crate::config_schema::CONFIG_FILE_NAME),
+    ]
 }
 
 /// Recursively looks for forgeconfig.json in the workspace roots or .vscode directories.
 #[cfg(not(target_arch = "wasm32"))]
 pub fn load_forge_config_full(workspace_folders: &[PathBuf]) -> Option<(ForgeConfig, PathBuf)> {
     for folder in workspace_folders {
-        let possible_paths = [
-            folder.join("forgeconfig.json"),
-            folder.join(".vscode").join("forgeconfig.json"),
-        ];
-
-        for path in possible_paths {
+        for path in config_file_paths(folder) {
             if !path.exists() {
                 continue;
             }
@@ -254,12 +319,15 @@ pub fn load_forge_config_full(workspace_folders: &[PathBuf]) -> Option<(ForgeCon
                 );
                 continue;
             };
-            let Ok(mut raw) = serde_json::from_str::<ForgeConfig>(&data) else {
-                crate::utils::forge_log(
-                    crate::utils::LogLevel::Error,
-                    &format!("Invalid JSON in config at {}", path.display()),
-                );
-                continue;
+            let mut raw = match serde_json::from_str::<ForgeConfig>(&data) {
+                Ok(raw) => raw,
+                Err(e) => {
+                    crate::utils::forge_log(
+                        crate::utils::LogLevel::Error,
+                        &format!("Invalid config at {}: {e}", path.display()),
+                    );
+                    continue;
+                }
             };
 
             raw.urls = raw.urls.into_iter().map(resolve_github_shorthand).collect();
@@ -279,7 +347,7 @@ pub fn parse_forge_config(json: &str) -> Option<ForgeConfig> {
 }
 
 /// Transforms github: shorthand into raw.githubusercontent.com URLs.
-fn resolve_github_shorthand(input: String) -> String {
+pub(crate) fn resolve_github_shorthand(input: String) -> String {
     if !input.starts_with("github:") {
         return input;
     }
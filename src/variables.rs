@@ -0,0 +1,244 @@
+//! Tracking of ForgeScript variables across a document.
+//!
+//! `$let`/`$get` variables live for one code block, while `$setVar`/`$getVar`
+//! variables are global. The index is built from the argument trees of the
+//! parsed functions and drives variable completion, go-to-definition and the
+//! undefined/unused variable warnings.
+
+use crate::parser::{Diagnostic, ParsedArg, ParsedFunction, Severity, code_blocks};
+
+/// Functions that define (`true`) or read (`false`) a block-scoped variable.
+const LOCAL_FUNCTIONS: [(&str, bool); 2] = [("let", true), ("get", false)];
+/// Functions that define (`true`) or read (`false`) a global variable.
+const GLOBAL_FUNCTIONS: [(&str, bool); 2] = [("setVar", true), ("getVar", false)];
+
+/// Whether a variable lives in one code block or in the global store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VarScope {
+    Local,
+    Global,
+}
+
+/// A variable name written as the first argument of a variable function.
+#[derive(Debug, Clone)]
+pub struct VarRef {
+    pub name: String,
+    /// Byte span of the name argument.
+    pub span: (usize, usize),
+    pub scope: VarScope,
+    pub is_definition: bool,
+    /// Index of the enclosing code block; global variables ignore it.
+    pub block: usize,
+}
+
+/// Every variable definition and use in a document.
+#[derive(Debug, Clone, Default)]
+pub struct VariableIndex {
+    pub refs: Vec<VarRef>,
+}
+
+impl VariableIndex {
+    /// Collects the variable references of the parsed functions of `text`.
+    ///
+    /// Names computed by nested functions are not tracked.
+    pub fn build(text: &str, functions: &[ParsedFunction]) -> Self {
+        let blocks = code_blocks(text);
+        let block_of = |pos: usize| {
+            blocks
+                .iter()
+                .position(|&(start, end)| start <= pos && pos <= end)
+                .unwrap_or(0)
+        };
+
+        let mut refs: Vec<VarRef> = functions
+            .iter()
+            .filter_map(|func| {
+                let (scope, is_definition) = classify(&func.name)?;
+                let (parts, span) = func.args.as_ref()?.first()?;
+                let literal = matches!(parts.as_slice(), [ParsedArg::Literal { .. }]);
+                let name = text.get(span.0..span.1)?;
+                if !literal || name.is_empty() {
+                    return None;
+                }
+                Some(VarRef {
+                    name: name.to_string(),
+                    span: *span,
+                    scope,
+                    is_definition,
+                    block: block_of(span.0),
+                })
+            })
+            .collect();
+        refs.sort_by_key(|r| r.span.0);
+        Self { refs }
+    }
+
+    /// Warns about block variables that are read without a `$let` or never read.
+    ///
+    /// Global variables may be set by other files or a database, so they are not checked.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for var in self.refs.iter().filter(|r| r.scope == VarScope::Local) {
+            let same = |other: &&VarRef| {
+                other.scope == VarScope::Local && other.block == var.block && other.name == var.name
+            };
+            let message = if var.is_definition {
+                if self.refs.iter().filter(same).any(|o| !o.is_definition) {
+                    continue;
+                }
+                format!("Variable `{}` is defined but never read", var.name)
+            } else {
+                let mut definitions = self.refs.iter().filter(same).filter(|o| o.is_definition);
+                match definitions.next() {
+                    None => format!("Variable `{}` is never defined with $let", var.name),
+                    Some(first) if first.span.0 > var.span.0 => {
+                        format!("Variable `{}` is read before its $let", var.name)
+                    }
+                    Some(_) => continue,
+                }
+            };
+            diagnostics.push(Diagnostic {
+                message,
+                start: var.span.0,
+                end: var.span.1,
+                severity: Severity::Warning,
+                code: None,
+            });
+        }
+        diagnostics
+    }
+
+    /// Returns the reference whose name contains `offset`.
+    pub fn reference_at(&self, offset: usize) -> Option<&VarRef> {
+        self.refs
+            .iter()
+            .find(|r| r.span.0 <= offset && offset <= r.span.1)
+    }
+
+    /// Finds the definition a variable reference resolves to.
+    ///
+    /// Block variables resolve to the closest preceding `$let`, falling back to the first one.
+    pub fn definition_of(&self, var: &VarRef) -> Option<&VarRef> {
+        let mut candidates = self.refs.iter().filter(|o| {
+            o.is_definition
+                && o.scope == var.scope
+                && o.name == var.name
+                && (var.scope == VarScope::Global || o.block == var.block)
+        });
+        let first = candidates.clone().next();
+        candidates.rfind(|o| o.span.0 <= var.span.0).or(first)
+    }
+
+    /// Lists the names defined in `scope` that are visible at `offset`, without duplicates.
+    pub fn names_visible_at(&self, text: &str, offset: usize, scope: VarScope) -> Vec<String> {
+        let block = code_blocks(text)
+            .iter()
+            .position(|&(start, end)| start <= offset && offset <= end)
+            .unwrap_or(0);
+        let mut names: Vec<String> = Vec::new();
+        for var in &self.refs {
+            if var.is_definition
+                && var.scope == scope
+                && (scope == VarScope::Global || var.block == block)
+                && !names.contains(&var.name)
+            {
+                names.push(var.name.clone());
+            }
+        }
+        names
+    }
+}
+
+/// Returns the scope of a variable function and whether it defines the variable.
+pub fn classify(function: &str) -> Option<(VarScope, bool)> {
+    let find = |table: &[(&str, bool)]| {
+        table
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(function))
+            .map(|&(_, defines)| defines)
+    };
+    find(&LOCAL_FUNCTIONS)
+        .map(|defines| (VarScope::Local, defines))
+        .or_else(|| find(&GLOBAL_FUNCTIONS).map(|defines| (VarScope::Global, defines)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata::test_manager;
+    use crate::parser::ForgeScriptParser;
+
+    fn index(code: &str) -> (String, VariableIndex) {
+        let manager = test_manager(&[
+            ("let", &["name", "value"]),
+            ("get", &["name"]),
+            ("setVar", &["name", "value"]),
+            ("getVar", &["name"]),
+        ]);
+        let parsed = ForgeScriptParser::new(manager, code).parse();
+        (
+            code.to_string(),
+            VariableIndex::build(code, &parsed.functions),
+        )
+    }
+
+    fn messages(code: &str) -> Vec<(String, String)> {
+        let (text, index) = index(code);
+        index
+            .diagnostics()
+            .into_iter()
+            .map(|d| (d.message, text[d.start..d.end].to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn warns_about_undefined_get() {
+        assert_eq!(
+            messages("code: `$get[x]`"),
+            [(
+                "Variable `x` is never defined with $let".to_string(),
+                "x".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn warns_about_unused_let() {
+        assert_eq!(
+            messages("code: `$let[x;1]`"),
+            [(
+                "Variable `x` is defined but never read".to_string(),
+                "x".to_string()
+            )]
+        );
+    }
+
+    #[test]
+    fn warns_about_read_before_let() {
+        assert_eq!(
+            messages("code: `$get[x]$let[x;1]`"),
+            [(
+                "Variable `x` is read before its $let".to_string(),
+                "x".to_string()
+            )]
+        );
+        assert!(messages("code: `$let[x;1]$get[x]`").is_empty());
+    }
+
+    #[test]
+    fn block_variables_do_not_leak_between_code_blocks() {
+        let found = messages("code: `$let[x;1]`\ncode: `$get[x]`");
+        assert_eq!(found.len(), 2);
+    }
+
+    #[test]
+    fn global_variables_resolve_across_code_blocks() {
+        let (text, index) = index("code: `$setVar[y;1]`\ncode: `$getVar[y]`");
+        assert!(index.diagnostics().is_empty());
+        let read = index.reference_at(text.rfind('y').unwrap()).unwrap();
+        assert_eq!(read.scope, VarScope::Global);
+        let definition = index.definition_of(read).unwrap();
+        assert!(definition.is_definition);
+        assert_eq!(definition.span.0, text.find('y').unwrap());
+    }
+}
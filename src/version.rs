@@ -0,0 +1,175 @@
+//! Version awareness for ForgeScript metadata.
+//!
+//! Parses the `version` field of function metadata, detects the ForgeScript
+//! package installed in the workspace, and flags calls to functions that are
+//! newer than the installed release.
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+use serde_json::Value as JsonValue;
+
+use crate::parser::{Diagnostic, ParsedFunction, Severity};
+
+/// npm package that provides the ForgeScript runtime.
+pub const FORGESCRIPT_PACKAGE: &str = "@tryforge/forgescript";
+
+/// A `major.minor.patch` version; pre-release and build suffixes are ignored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ForgeVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl ForgeVersion {
+    /// Parses strings such as `1.4.0`, `v2.1`, `^1.3.2` or `1.5.0-dev.3`.
+    pub fn parse(input: &str) -> Option<Self> {
+        let trimmed = input
+            .trim()
+            .trim_start_matches(['^', '~', '>', '<', '=', 'v', ' ']);
+        let core = trimmed.split(['-', '+']).next().unwrap_or_default();
+        if core.is_empty() {
+            return None;
+        }
+
+        let mut parts = core.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = match parts.next() {
+            Some(p) => p.parse().ok()?,
+            None => 0,
+        };
+        let patch = match parts.next() {
+            Some(p) => p.parse().ok()?,
+            None => 0,
+        };
+        Some(Self {
+            major,
+            minor,
+            patch,
+        })
+    }
+
+    /// Reads a version from the raw `version` field of function metadata.
+    pub fn from_json(value: &JsonValue) -> Option<Self> {
+        match value {
+            JsonValue::String(s) => Self::parse(s),
+            _ => None,
+        }
+    }
+
+    /// Formats the version as `vX.Y` (or `vX.Y.Z` when a patch number is present).
+    pub fn short_label(&self) -> String {
+        if self.patch == 0 {
+            format!("v{}.{}", self.major, self.minor)
+        } else {
+            format!("v{}.{}.{}", self.major, self.minor, self.patch)
+        }
+    }
+}
+
+impl std::fmt::Display for ForgeVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// Returns the "since vX.Y" label for a function, if its version is known.
+pub fn since_label(func: &crate::metadata::Function) -> Option<String> {
+    if func.category.as_deref() == Some("custom") {
+        return None;
+    }
+    ForgeVersion::from_json(&func.version).map(|v| format!("since {}", v.short_label()))
+}
+
+/// Whether a function's version should be compared against the installed ForgeScript version.
+fn is_core_function(func: &crate::metadata::Function) -> bool {
+    func.category.as_deref() != Some("custom")
+        && func
+            .extension
+            .as_deref()
+            .is_none_or(|ext| ext.eq_ignore_ascii_case("forgescript"))
+}
+
+/// Emits a warning for every call to a function introduced after the installed version.
+pub fn check_function_versions(
+    functions: &[ParsedFunction],
+    installed: ForgeVersion,
+) -> Vec<Diagnostic> {
+    functions
+        .iter()
+        .filter(|f| is_core_function(&f.meta))
+        .filter_map(|f| {
+            let required = ForgeVersion::from_json(&f.meta.version)?;
+            if required <= installed {
+                return None;
+            }
+            Some(Diagnostic {
+                message: format!(
+                    "${} requires ForgeScript {}, but {} is installed",
+                    f.name,
+                    required.short_label(),
+                    installed.short_label()
+                ),
+                start: f.span.0,
+                end: f.span.0 + f.matched.len(),
+                severity: Severity::Warning,
+                code: None,
+            })
+        })
+        .collect()
+}
+
+// ── Installed Version Detection (Native) ────────────────────────────────────
+
+/// Finds the installed ForgeScript version in the given workspace folders.
+///
+/// Prefers the exact version from `node_modules`, falling back to the
+/// dependency range declared in `package.json`.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn detect_installed_version(workspace_folders: &[PathBuf]) -> Option<ForgeVersion> {
+    for folder in workspace_folders {
+        let installed_manifest = folder
+            .join("node_modules")
+            .join(FORGESCRIPT_PACKAGE)
+            .join("package.json");
+        if let Some(version) = read_json(&installed_manifest)
+            .and_then(|json| json.get("version")?.as_str().and_then(ForgeVersion::parse))
+        {
+            crate::utils::forge_log(
+                crate::utils::LogLevel::Debug,
+                &format!(
+                    "Detected ForgeScript {version} from {}",
+                    installed_manifest.display()
+                ),
+            );
+            return Some(version);
+        }
+
+        let project_manifest = folder.join("package.json");
+        if let Some(json) = read_json(&project_manifest) {
+            let declared = ["dependencies", "devDependencies", "peerDependencies"]
+                .iter()
+                .find_map(|section| json.get(section)?.get(FORGESCRIPT_PACKAGE)?.as_str());
+            if let Some(version) = declared.and_then(ForgeVersion::parse) {
+                crate::utils::forge_log(
+                    crate::utils::LogLevel::Debug,
+                    &format!(
+                        "Detected ForgeScript {version} from {}",
+                        project_manifest.display()
+                    ),
+                );
+                return Some(version);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_json(path: &std::path::Path) -> Option<JsonValue> {
+    let data = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data).ok()
+}
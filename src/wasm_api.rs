@@ -7,7 +7,7 @@ use std::sync::Arc;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::future_to_promise;
 
-use crate::metadata::MetadataManager;
+use crate::metadata::{DEFAULT_FUNCTIONS_URL, MetadataManager};
 use crate::parser::ForgeScriptParser;
 use crate::utils;
 use lsp_types::Position;
@@ -27,17 +27,11 @@ pub fn init(config_json: &str) -> Result<(), JsValue> {
     utils::forge_log(utils::LogLevel::Info, "ForgeLSP WASM module initializing");
 
     let fetch_urls = if config_json.is_empty() {
-        vec![
-            "https://raw.githubusercontent.com/tryforge/forgescript/dev/metadata/functions.json"
-                .to_string(),
-        ]
+        vec![DEFAULT_FUNCTIONS_URL.to_string()]
     } else {
         utils::parse_forge_config(config_json)
             .map(|config| config.urls)
-            .unwrap_or_else(|| {
-                vec!["https://raw.githubusercontent.com/tryforge/forgescript/dev/metadata/functions.json"
-                    .to_string()]
-            })
+            .unwrap_or_else(|| vec![DEFAULT_FUNCTIONS_URL.to_string()])
     };
 
     let mgr = MetadataManager::new_wasm(fetch_urls)
@@ -118,6 +112,7 @@ pub fn process_document(text: &str) -> Result<JsValue, JsValue> {
                 "start": d.start,
                 "end": d.end,
                 "message": d.message,
+                "severity": d.severity,
             })
         }).collect::<Vec<_>>(),
     });
@@ -198,6 +193,22 @@ pub fn get_all_function_names() -> Result<JsValue, JsValue> {
         .map_err(|e| JsValue::from_str(&format!("Serialize error: {e}")))
 }
 
+/// Returns the JSON Schema for `forgeconfig.json` as a JSON string.
+#[wasm_bindgen]
+pub fn get_config_schema() -> Result<JsValue, JsValue> {
+    serde_json::to_string(&crate::config_schema::config_schema())
+        .map(|s| JsValue::from_str(&s))
+        .map_err(|e| JsValue::from_str(&format!("Serialize error: {e}")))
+}
+
+/// Validates `forgeconfig.json` text, returning diagnostics as a JSON array.
+#[wasm_bindgen]
+pub fn validate_config(text: &str) -> Result<JsValue, JsValue> {
+    serde_json::to_string(&crate::config_schema::validate_config(text))
+        .map(|s| JsValue::from_str(&s))
+        .map_err(|e| JsValue::from_str(&format!("Serialize error: {e}")))
+}
+
 /// Converts a byte offset to (line, character) in the given text.
 #[wasm_bindgen]
 pub fn offset_to_position(text: &str, offset: usize) -> JsValue {
@@ -226,7 +237,8 @@ pub fn get_semantic_tokens(text: &str, use_colors: bool) -> Result<JsValue, JsVa
         .get()
         .ok_or_else(|| JsValue::from_str("Not initialized — call init() first"))?;
 
-    let tokens = crate::semantic::extract_semantic_tokens_with_colors(text, use_colors, mgr);
+    let parsed = ForgeScriptParser::new(mgr.clone(), text).parse();
+    let tokens = crate::semantic::semantic_tokens_from_parse(text, &parsed, use_colors, mgr);
 
     let data: Vec<u32> = tokens
         .iter()
@@ -271,7 +283,9 @@ pub fn get_highlight_ranges(text: &str) -> Result<JsValue, JsValue> {
         "#b4befe".to_string(),
     ];
 
-    let highlights = crate::semantic::extract_highlight_ranges(text, &default_colors, true, mgr);
+    let parsed = ForgeScriptParser::new(mgr.clone(), text).parse();
+    let highlights =
+        crate::semantic::highlight_ranges_from_parse(text, &parsed, &default_colors, true);
 
     serde_json::to_string(&highlights)
         .map(|s| JsValue::from_str(&s))
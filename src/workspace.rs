@@ -0,0 +1,153 @@
+//! Per-folder configuration for multi-root workspaces.
+//!
+//! Each workspace folder may carry its own `forgeconfig.json`, metadata sources
+//! and custom functions. Documents are routed to the folder that owns them;
+//! folders without a configuration inherit the first one found in the workspace,
+//! and documents outside every folder use the first folder's state.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Result;
+use tower_lsp::Client;
+
+use crate::custom_js::{ClientSetup, extract_client_setup};
+use crate::metadata::MetadataManager;
+use crate::utils::{ForgeConfig, load_forge_config_full};
+use crate::version::{ForgeVersion, detect_installed_version};
+
+/// Configuration, metadata and package information scoped to one workspace folder.
+#[derive(Debug, Clone)]
+pub struct WorkspaceFolderState {
+    pub root: PathBuf,
+    pub config: Option<ForgeConfig>,
+    pub config_dir: Option<PathBuf>,
+    /// Shared by every folder that resolves the same configuration directory.
+    pub manager: Arc<MetadataManager>,
+    pub installed_version: Option<ForgeVersion>,
+    /// The parsed `client_path` file, refreshed when the file watcher reports a change.
+    pub client_setup: Option<(PathBuf, ClientSetup)>,
+}
+
+impl WorkspaceFolderState {
+    /// Resolves the folder's configuration and builds a manager from the disk cache.
+    ///
+    /// A folder resolving the same configuration directory as one in `existing` reuses
+    /// its manager.
+    pub fn load(
+        root: PathBuf,
+        fallback: Option<&(ForgeConfig, PathBuf)>,
+        existing: &[WorkspaceFolderState],
+        client: Option<Client>,
+    ) -> Result<Self> {
+        let installed_version = detect_installed_version(std::slice::from_ref(&root));
+        let (config, config_dir) = folder_config(&root, fallback).unzip();
+        let manager = match existing.iter().find(|s| s.config_dir == config_dir) {
+            Some(state) => state.manager.clone(),
+            None => Arc::new(cached_manager(
+                config.as_ref().zip(config_dir.as_deref()),
+                client,
+            )?),
+        };
+        let mut state = Self {
+            root,
+            config,
+            config_dir,
+            manager,
+            installed_version,
+            client_setup: None,
+        };
+        state.refresh_client_setup();
+        Ok(state)
+    }
+
+    /// The client setup file the folder's configuration points at, if any.
+    pub fn client_path(&self) -> Option<PathBuf> {
+        let (config, config_dir) = self.loaded_config()?;
+        Some(config_dir.join(config.client_path.as_ref()?))
+    }
+
+    /// Re-reads and re-parses the client setup file.
+    pub fn refresh_client_setup(&mut self) {
+        self.client_setup = self.client_path().and_then(|path| {
+            let source = std::fs::read_to_string(&path).ok()?;
+            Some((path, extract_client_setup(&source)?))
+        });
+    }
+
+    /// Returns the metadata source URLs configured for this folder, if any.
+    pub fn urls(&self) -> Option<&[String]> {
+        self.config.as_ref().map(|c| c.urls.as_slice())
+    }
+
+    /// Returns the folder's configuration together with the directory it was loaded from.
+    pub fn loaded_config(&self) -> Option<(&ForgeConfig, &Path)> {
+        Some((self.config.as_ref()?, self.config_dir.as_deref()?))
+    }
+
+    /// Whether a changed JS/TS file lies inside this folder's custom functions directory.
+    pub fn owns_custom_function_file(&self, path: &Path) -> bool {
+        let Some((config, config_dir)) = self.loaded_config() else {
+            return false;
+        };
+        config
+            .custom_functions_path
+            .as_ref()
+            .is_some_and(|rel| path.starts_with(config_dir.join(rel)))
+    }
+}
+
+/// Reads the folder's own `forgeconfig.json`, or the workspace-wide `fallback` without one.
+pub fn folder_config(
+    root: &Path,
+    fallback: Option<&(ForgeConfig, PathBuf)>,
+) -> Option<(ForgeConfig, PathBuf)> {
+    load_forge_config_full(&[root.to_path_buf()]).or_else(|| fallback.cloned())
+}
+
+/// Builds a manager for a configuration using cached metadata and its custom functions.
+///
+/// Without a configuration the manager serves the default metadata source.
+pub fn cached_manager(
+    loaded: Option<(&ForgeConfig, &Path)>,
+    client: Option<Client>,
+) -> Result<MetadataManager> {
+    let (urls, cache_ttl) = match loaded {
+        Some((config, _)) => (config.urls.clone(), config.cache_ttl()),
+        None => (
+            vec![crate::metadata::DEFAULT_FUNCTIONS_URL.to_string()],
+            None,
+        ),
+    };
+    let manager = MetadataManager::new("./.cache", urls, client, cache_ttl)?;
+    manager.load_from_cache();
+    if let Some((config, config_dir)) = loaded
+        && let Err(e) = manager.load_custom_functions_from_config(config, config_dir)
+    {
+        crate::utils::forge_log(
+            crate::utils::LogLevel::Warn,
+            &format!("Custom function load failed: {e}"),
+        );
+    }
+    Ok(manager)
+}
+
+/// Finds the folder owning `path`, preferring the most deeply nested root.
+pub fn owning_folder<'a>(
+    folders: &'a [WorkspaceFolderState],
+    path: &Path,
+) -> Option<&'a WorkspaceFolderState> {
+    folders
+        .iter()
+        .filter(|f| path.starts_with(&f.root))
+        .max_by_key(|f| f.root.components().count())
+}
+
+/// Finds the folder owning `path`, or the first folder for documents outside the workspace.
+pub fn folder_for<'a>(
+    folders: &'a [WorkspaceFolderState],
+    path: Option<&Path>,
+) -> Option<&'a WorkspaceFolderState> {
+    path.and_then(|path| owning_folder(folders, path))
+        .or_else(|| folders.first())
+}